@@ -0,0 +1,90 @@
+/*
+Perft throughput benchmark, run with `cargo bench --bench perft`.
+
+Plain std timing, every position is counted a few times and the fastest run is reported so numbers are comparable
+between changes to move generation.
+*/
+
+use oracle::bitboards::{self, tables};
+use oracle::moves::info::MoveList;
+use oracle::moves::{count_legal_moves, generate_moves};
+use oracle::position::Position;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const RUNS: u32 = 5;
+const GENERATE_ITERATIONS: u32 = 1_000_000;
+
+// https://www.chessprogramming.org/Perft_Results
+const POSITIONS: [(&str, &str, u32, u64); 4] = [
+    (
+        "start",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        5,
+        4_865_609,
+    ),
+    (
+        "kiwipete",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        4,
+        4_085_603,
+    ),
+    ("position 3", "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 5, 674_624),
+    (
+        "position 4",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        4,
+        422_333,
+    ),
+];
+
+fn main() {
+    tables::force_load(&bitboards::LOOKUP_TABLES);
+
+    println!(
+        "{:<12} {:>6} {:>12} {:>12} {:>14}",
+        "position", "depth", "nodes", "time", "nodes/second"
+    );
+    let mut total_nodes = 0;
+    let mut total_time = Duration::default();
+    for (name, fen, depth, expected) in POSITIONS {
+        let mut position = Position::new(fen);
+        let mut best = Duration::MAX;
+        for _ in 0..RUNS {
+            let start = Instant::now();
+            let nodes = count_legal_moves(black_box(&mut position), depth);
+            best = best.min(start.elapsed());
+            assert_eq!(nodes, expected, "perft mismatch for {}", name);
+        }
+
+        total_nodes += expected;
+        total_time += best;
+        println!(
+            "{:<12} {:>6} {:>12} {:>12?} {:>14.0}",
+            name,
+            depth,
+            expected,
+            best,
+            expected as f64 / best.as_secs_f64()
+        );
+    }
+    println!(
+        "{:<12} {:>6} {:>12} {:>12?} {:>14.0}",
+        "total",
+        "",
+        total_nodes,
+        total_time,
+        total_nodes as f64 / total_time.as_secs_f64()
+    );
+
+    // move generation alone, without making moves
+    let position = Position::new(POSITIONS[1].1);
+    let mut moves = MoveList::default();
+    let start = Instant::now();
+    for _ in 0..GENERATE_ITERATIONS {
+        generate_moves(black_box(&position), &mut moves);
+        black_box(moves.len());
+    }
+    let elapsed = start.elapsed();
+    println!("\ngenerate_moves (kiwipete): {:?}/call", elapsed / GENERATE_ITERATIONS);
+}
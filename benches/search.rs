@@ -0,0 +1,77 @@
+/*
+Fixed-depth search benchmark, run with `cargo bench --bench search`.
+
+The node count is what move ordering is judged by: a better order cuts off earlier and visits fewer nodes for the same
+depth. Every search starts from an empty table so the counts are reproducible.
+*/
+
+use oracle::bitboards::{self, tables};
+use oracle::position::Position;
+use oracle::search::{self, SearchLimits};
+use std::time::Duration;
+
+const POSITIONS: [(&str, &str, u32); 6] = [
+    ("start", "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 7),
+    (
+        "kiwipete",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        5,
+    ),
+    ("position 3", "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 8),
+    (
+        "position 4",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        5,
+    ),
+    (
+        "position 5",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        5,
+    ),
+    (
+        "middlegame",
+        "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N2N2/PP2BPPP/R2QKB1R w KQ - 0 8",
+        6,
+    ),
+];
+
+fn main() {
+    tables::force_load(&bitboards::LOOKUP_TABLES);
+
+    println!(
+        "{:<12} {:>6} {:>12} {:>12} {:>14}  best move",
+        "position", "depth", "nodes", "time", "nodes/second"
+    );
+    let mut total_nodes = 0;
+    let mut total_time = Duration::default();
+    for (name, fen, depth) in POSITIONS {
+        let mut position = Position::new(fen);
+        let limits = SearchLimits {
+            depth,
+            ..SearchLimits::default()
+        };
+        let result = search::search(&mut position, &limits);
+
+        total_nodes += result.nodes;
+        total_time += result.elapsed;
+        println!(
+            "{:<12} {:>6} {:>12} {:>12?} {:>14.0}  {}",
+            name,
+            depth,
+            result.nodes,
+            result.elapsed,
+            result.nodes as f64 / result.elapsed.as_secs_f64(),
+            result
+                .best_move
+                .map_or("none".into(), |mv| position.format_uci_move(mv))
+        );
+    }
+    println!(
+        "{:<12} {:>6} {:>12} {:>12?} {:>14.0}",
+        "total",
+        "",
+        total_nodes,
+        total_time,
+        total_nodes as f64 / total_time.as_secs_f64()
+    );
+}
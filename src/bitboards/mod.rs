@@ -12,10 +12,10 @@ pub type Bitboard = u64;
 
 pub static LOOKUP_TABLES: tables::LookupTables = tables::LookupTables::init();
 
+#[derive(Clone)]
 pub struct Bitboards {
     legal_moves: [Bitboard; Square::Count as usize],
     checkers: [Bitboard; Color::Both as usize],
-    attacks: [Bitboard; Color::Both as usize],
 }
 
 impl Default for Bitboards {
@@ -23,24 +23,20 @@ impl Default for Bitboards {
         Bitboards {
             legal_moves: [0; Square::Count as usize],
             checkers: [0; Color::Both as usize],
-            attacks: [0; Color::Both as usize],
         }
     }
 }
 
 impl Bitboards {
-    pub fn set_legal_moves(&mut self, sq: Square, bb: Bitboard) {
+    pub(crate) fn set_legal_moves(&mut self, sq: Square, bb: Bitboard) {
         self.legal_moves[sq as usize] = bb;
     }
 
-    pub fn get_legal_moves(&self, sq: Square) -> Bitboard {
+    // only meaningful while the owning position's cache is fresh, go through `Position::legal_destinations_from`
+    pub(crate) fn get_legal_moves(&self, sq: Square) -> Bitboard {
         self.legal_moves[sq as usize]
     }
 
-    pub fn is_legal_move(&self, from: Square, to: Square) -> bool {
-        is_bit_set(self.legal_moves[from as usize], to)
-    }
-
     pub fn get_checkers(&self, color: Color) -> Bitboard {
         if color == Color::Both {
             return self.checkers[Color::White as usize] | self.checkers[Color::Black as usize];
@@ -66,19 +62,6 @@ impl Bitboards {
         }
         is_bit_set(self.checkers[color as usize], sq)
     }
-
-    pub fn set_attacks(&mut self, color: Color, attacks: Bitboard) {
-        if color != Color::Both {
-            self.attacks[color as usize] = attacks;
-        }
-    }
-
-    pub fn get_attacks(&self, color: Color) -> Bitboard {
-        if color == Color::Both {
-            return self.attacks[Color::White as usize] | self.attacks[Color::Black as usize];
-        }
-        self.attacks[color as usize]
-    }
 }
 
 #[allow(dead_code)]
@@ -0,0 +1,87 @@
+/*
+https://www.chessprogramming.org/Chess960
+https://en.wikipedia.org/wiki/Fischer_random_chess_numbering_scheme
+
+Start positions are numbered 0 to 959 like Scharnagl did, 518 is the standard start position. The number is taken
+apart digit by digit: the light squared bishop, the dark squared bishop, the queen, then one of ten ways to place the
+knights on the remaining five squares. Rook, king and rook fill the last three squares in that order.
+*/
+
+use crate::position::Position;
+use crate::utils::PieceType;
+use rand::Rng;
+
+pub const START_POSITIONS: u16 = 960;
+pub const STANDARD_START_POSITION: u16 = 518;
+
+// knight squares among the five left after placing bishops and queen
+const KNIGHT_PLACEMENTS: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+
+// White's back rank from the a- to the h-file, black mirrors it
+pub fn back_rank(id: u16) -> [PieceType; 8] {
+    assert!(id < START_POSITIONS, "no chess960 start position {}", id);
+
+    let mut rank = [PieceType::Empty; 8];
+    let mut n = id as usize;
+    rank[n % 4 * 2 + 1] = PieceType::Bishop;
+    n /= 4;
+    rank[n % 4 * 2] = PieceType::Bishop;
+    n /= 4;
+    place_on_empty(&mut rank, n % 6, PieceType::Queen);
+    n /= 6;
+
+    // placing the first knight shifts the second one's index down by one
+    let (first, second) = KNIGHT_PLACEMENTS[n];
+    place_on_empty(&mut rank, first, PieceType::Knight);
+    place_on_empty(&mut rank, second - 1, PieceType::Knight);
+
+    for piece_type in [PieceType::Rook, PieceType::King, PieceType::Rook] {
+        place_on_empty(&mut rank, 0, piece_type);
+    }
+    rank
+}
+
+pub fn start_fen(id: u16) -> String {
+    let white = back_rank(id)
+        .iter()
+        .map(|&piece_type| PieceType::to_char(piece_type).to_ascii_uppercase())
+        .collect::<String>();
+    format!(
+        "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1",
+        white.to_ascii_lowercase(),
+        white
+    )
+}
+
+// In Chess960 mode, even for the standard start position
+pub fn start_position(id: u16) -> Position {
+    let mut position = Position::new(&start_fen(id));
+    position.set_chess960(true);
+    position
+}
+
+pub fn random_id() -> u16 {
+    rand::thread_rng().gen_range(0..START_POSITIONS)
+}
+
+fn place_on_empty(rank: &mut [PieceType; 8], nth_empty: usize, piece_type: PieceType) {
+    let file = rank
+        .iter()
+        .enumerate()
+        .filter(|&(_, &occupant)| occupant == PieceType::Empty)
+        .nth(nth_empty)
+        .map(|(file, _)| file)
+        .expect("not enough empty squares on the back rank");
+    rank[file] = piece_type;
+}
@@ -0,0 +1,291 @@
+/*
+https://www.chessprogramming.org/Simplified_Evaluation_Function
+https://www.chessprogramming.org/Tapered_Eval
+*/
+
+use crate::position::Position;
+use crate::utils::{Color, PieceType, Square};
+use num_traits::FromPrimitive;
+use std::fmt;
+use std::ops::{Add, AddAssign, Sub};
+
+// game phase contribution of each piece type, indexed by PieceType. The starting position has the maximum phase.
+const PHASE_WEIGHTS: [i32; PieceType::Pawn as usize + 1] = [0, 0, 4, 1, 1, 2, 0];
+const MAX_PHASE: i32 = 24;
+
+const MATERIAL: [Score; PieceType::Pawn as usize + 1] = [
+    Score::new(0, 0),     // Empty
+    Score::new(0, 0),     // King
+    Score::new(900, 950), // Queen
+    Score::new(330, 340), // Bishop
+    Score::new(320, 300), // Knight
+    Score::new(500, 530), // Rook
+    Score::new(100, 120), // Pawn
+];
+
+// One value per piece type where a tapered score is overkill, e.g. ordering captures. The king has no material value.
+pub const PIECE_VALUES: [i32; PieceType::Pawn as usize + 1] = [0, 0, 900, 330, 320, 500, 100];
+
+// Piece-square tables are written from white's point of view with rank 8 on top, so a1 is the first entry of the
+// last row. Black pieces use the vertically mirrored square.
+#[rustfmt::skip]
+const PAWN_MG: [i32; Square::Count as usize] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    50,  50,  50,  50,  50,  50,  50,  50,
+    10,  10,  20,  30,  30,  20,  10,  10,
+     5,   5,  10,  25,  25,  10,   5,   5,
+     0,   0,   0,  20,  20,   0,   0,   0,
+     5,  -5, -10,   0,   0, -10,  -5,   5,
+     5,  10,  10, -20, -20,  10,  10,   5,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const PAWN_EG: [i32; Square::Count as usize] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    80,  80,  80,  80,  80,  80,  80,  80,
+    50,  50,  50,  50,  50,  50,  50,  50,
+    30,  30,  30,  30,  30,  30,  30,  30,
+    15,  15,  15,  15,  15,  15,  15,  15,
+     5,   5,   5,   5,   5,   5,   5,   5,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const KNIGHT: [i32; Square::Count as usize] = [
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20,   0,   0,   0,   0, -20, -40,
+    -30,   0,  10,  15,  15,  10,   0, -30,
+    -30,   5,  15,  20,  20,  15,   5, -30,
+    -30,   0,  15,  20,  20,  15,   0, -30,
+    -30,   5,  10,  15,  15,  10,   5, -30,
+    -40, -20,   0,   5,   5,   0, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+#[rustfmt::skip]
+const BISHOP: [i32; Square::Count as usize] = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const ROOK: [i32; Square::Count as usize] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+     5,  10,  10,  10,  10,  10,  10,   5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+     0,   0,   0,   5,   5,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const QUEEN: [i32; Square::Count as usize] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+      0,   0,   5,   5,   5,   5,   0,  -5,
+    -10,   5,   5,   5,   5,   5,   0, -10,
+    -10,   0,   5,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const KING_MG: [i32; Square::Count as usize] = [
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+     20,  20,   0,   0,   0,   0,  20,  20,
+     20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+#[rustfmt::skip]
+const KING_EG: [i32; Square::Count as usize] = [
+    -50, -40, -30, -20, -20, -30, -40, -50,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+// being in check usually means the side to move has to spend a tempo on defending
+const IN_CHECK_PENALTY: Score = Score::new(-30, -10);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Score {
+    pub mg: i32,
+    pub eg: i32,
+}
+
+impl Score {
+    pub const fn new(mg: i32, eg: i32) -> Score {
+        Score { mg, eg }
+    }
+
+    pub fn taper(&self, phase: i32) -> i32 {
+        (self.mg * phase + self.eg * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+
+impl Add for Score {
+    type Output = Score;
+    fn add(self, rhs: Score) -> Score {
+        Score::new(self.mg + rhs.mg, self.eg + rhs.eg)
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, rhs: Score) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Score {
+    type Output = Score;
+    fn sub(self, rhs: Score) -> Score {
+        Score::new(self.mg - rhs.mg, self.eg - rhs.eg)
+    }
+}
+
+// All scores are from white's point of view and indexed by Color
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EvalBreakdown {
+    pub material: [Score; Color::Both as usize],
+    pub piece_squares: [Score; Color::Both as usize],
+    pub in_check: [Score; Color::Both as usize],
+    pub phase: i32,
+    pub side_to_move: Color,
+}
+
+impl EvalBreakdown {
+    pub fn total(&self) -> Score {
+        let mut total = Score::default();
+        for color in [Color::White, Color::Black] {
+            let term =
+                self.material[color as usize] + self.piece_squares[color as usize] + self.in_check[color as usize];
+            if color == Color::White {
+                total += term;
+            } else {
+                total = total - term;
+            }
+        }
+        total
+    }
+
+    // final score from white's point of view
+    pub fn white_score(&self) -> i32 {
+        self.total().taper(self.phase)
+    }
+
+    // final score from the side to move's point of view
+    pub fn score(&self) -> i32 {
+        if self.side_to_move == Color::Black {
+            -self.white_score()
+        } else {
+            self.white_score()
+        }
+    }
+}
+
+impl fmt::Display for EvalBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let write_term = |f: &mut fmt::Formatter, name: &str, terms: &[Score; Color::Both as usize]| {
+            let white = terms[Color::White as usize];
+            let black = terms[Color::Black as usize];
+            let total = white - black;
+            writeln!(
+                f,
+                "{:<14}|{:>6} {:>6} |{:>6} {:>6} |{:>6} {:>6}",
+                name, white.mg, white.eg, black.mg, black.eg, total.mg, total.eg
+            )
+        };
+
+        writeln!(f, "{:<14}|{:^13} |{:^13} |{:^13}", "Term", "White", "Black", "Total")?;
+        writeln!(
+            f,
+            "{:<14}|{:>6} {:>6} |{:>6} {:>6} |{:>6} {:>6}",
+            "", "MG", "EG", "MG", "EG", "MG", "EG"
+        )?;
+        writeln!(f, "{}", "-".repeat(58))?;
+        write_term(f, "Material", &self.material)?;
+        write_term(f, "Piece-square", &self.piece_squares)?;
+        write_term(f, "In check", &self.in_check)?;
+        writeln!(f, "{}", "-".repeat(58))?;
+
+        let total = self.total();
+        writeln!(f, "{:<14}|{:14}|{:14}|{:>6} {:>6}", "Total", "", "", total.mg, total.eg)?;
+        writeln!(f, "Phase: {}/{}", self.phase, MAX_PHASE)?;
+        writeln!(f, "Final evaluation (white): {}", self.white_score())?;
+        write!(f, "Final evaluation (side to move): {}", self.score())
+    }
+}
+
+pub fn evaluate(pos: &Position) -> i32 {
+    evaluate_breakdown(pos).score()
+}
+
+pub fn evaluate_breakdown(pos: &Position) -> EvalBreakdown {
+    let mut breakdown = EvalBreakdown {
+        side_to_move: pos.side_to_move(),
+        ..EvalBreakdown::default()
+    };
+
+    let mut phase = 0;
+    for color in [Color::White, Color::Black] {
+        let friendly_pieces = pos.bitboards.get_checkers(color);
+        for piece_type in (PieceType::King as u8..=PieceType::Pawn as u8).filter_map(PieceType::from_u8) {
+            let mut pieces = pos.piece_masks[piece_type as usize] & friendly_pieces;
+            while pieces != 0 {
+                let sq = Square::from_u8(pieces.trailing_zeros() as u8).unwrap_or_default();
+                pieces &= pieces - 1;
+
+                breakdown.material[color as usize] += MATERIAL[piece_type as usize];
+                breakdown.piece_squares[color as usize] += piece_square(piece_type, color, sq);
+                phase += PHASE_WEIGHTS[piece_type as usize];
+            }
+        }
+
+        if pos.king_in_check(color) {
+            breakdown.in_check[color as usize] = IN_CHECK_PENALTY;
+        }
+    }
+
+    // promotions can push the phase past the starting value
+    breakdown.phase = phase.min(MAX_PHASE);
+    breakdown
+}
+
+fn piece_square(piece_type: PieceType, color: Color, sq: Square) -> Score {
+    // tables are laid out with rank 8 first, flip the rank for white
+    let idx = if color == Color::White {
+        sq as usize ^ 56
+    } else {
+        sq as usize
+    };
+
+    match piece_type {
+        PieceType::Pawn => Score::new(PAWN_MG[idx], PAWN_EG[idx]),
+        PieceType::Knight => Score::new(KNIGHT[idx], KNIGHT[idx]),
+        PieceType::Bishop => Score::new(BISHOP[idx], BISHOP[idx]),
+        PieceType::Rook => Score::new(ROOK[idx], ROOK[idx]),
+        PieceType::Queen => Score::new(QUEEN[idx], QUEEN[idx]),
+        PieceType::King => Score::new(KING_MG[idx], KING_EG[idx]),
+        PieceType::Empty => Score::default(),
+    }
+}
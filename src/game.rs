@@ -1,8 +1,11 @@
-use crate::bitboards;
-use crate::moves::compute::{KINGSIDE_CASTLE_SQUARES, QUEENSIDE_CASTLE_SQUARES};
+use crate::bitboards::Bitboard;
 use crate::moves::info::{Move, MoveInfo};
-use crate::position::Position;
-use crate::utils::{File, MoveType, Piece, PieceType, Rank, Square};
+use crate::position::{FenError, Position};
+use crate::utils::{Color, MoveType, Piece, PieceType, Square};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+const LIGHT_SQUARES: Bitboard = 0x55aa_55aa_55aa_55aa;
 
 #[derive(Clone, Debug, Default)]
 pub struct GameMove {
@@ -10,19 +13,125 @@ pub struct GameMove {
     pub notation: String,
 }
 
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum GameResult {
+    #[default]
+    Ongoing,
+    // the winning color
+    Checkmate(Color),
+    Stalemate,
+    ThreefoldRepetition,
+    FiftyMoveRule,
+    InsufficientMaterial,
+}
+
+impl GameResult {
+    // no further move can be played, or none of them can change the result
+    pub fn is_over(&self) -> bool {
+        matches!(
+            self,
+            GameResult::Checkmate(_) | GameResult::Stalemate | GameResult::InsufficientMaterial
+        )
+    }
+
+    // a draw either player may claim, play goes on until one does
+    pub fn is_claimable(&self) -> bool {
+        matches!(self, GameResult::ThreefoldRepetition | GameResult::FiftyMoveRule)
+    }
+
+    pub fn winner(&self) -> Option<Color> {
+        match self {
+            GameResult::Checkmate(color) => Some(*color),
+            _ => None,
+        }
+    }
+
+    // result tag as written in PGN
+    pub fn score(&self) -> &'static str {
+        match self {
+            GameResult::Ongoing => "*",
+            GameResult::Checkmate(Color::White) => "1-0",
+            GameResult::Checkmate(_) => "0-1",
+            _ => "1/2-1/2",
+        }
+    }
+
+    pub fn reason(&self) -> &'static str {
+        match self {
+            GameResult::Ongoing => "Ongoing",
+            GameResult::Checkmate(_) => "Checkmate",
+            GameResult::Stalemate => "Stalemate",
+            GameResult::ThreefoldRepetition => "Threefold repetition",
+            GameResult::FiftyMoveRule => "Fifty-move rule",
+            GameResult::InsufficientMaterial => "Insufficient material",
+        }
+    }
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_over() {
+            write!(f, "{} ({})", self.score(), self.reason())
+        } else if self.is_claimable() {
+            write!(f, "{} can be claimed", self.reason())
+        } else {
+            write!(f, "{}", self.reason())
+        }
+    }
+}
+
+// GameState keeps the legal moves of its position up to date, the outcome depends on them
+#[derive(Clone, Debug)]
 pub struct GameState {
     pub position: Position,
+    start_fen: String,
     move_history: Vec<GameMove>,
     redo_history: Vec<GameMove>,
 }
 
+// Games are equal when they reached the same position through the same moves. Where they started is implied by that,
+// the redo history is an editing aid and ignored.
+impl PartialEq for GameState {
+    fn eq(&self, other: &GameState) -> bool {
+        self.position == other.position && self.moves().eq(other.moves())
+    }
+}
+
+impl Eq for GameState {}
+
+impl Hash for GameState {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.position.hash(state);
+        for mv in self.moves() {
+            mv.hash(state);
+        }
+    }
+}
+
 impl GameState {
+    // Panics on invalid FEN, use `from_fen` for anything that comes from the user
     pub fn new(fen: &str) -> GameState {
-        GameState {
-            position: Position::new(fen),
+        GameState::from_fen(fen).unwrap_or_else(|err| panic!("invalid fen '{}': {}", fen, err))
+    }
+
+    pub fn from_fen(fen: &str) -> Result<GameState, FenError> {
+        let mut position = Position::from_fen(fen)?;
+        position.compute_legal_moves();
+        Ok(GameState {
+            position,
+            start_fen: fen.into(),
             move_history: Vec::new(),
             redo_history: Vec::new(),
-        }
+        })
+    }
+
+    pub fn start_fen(&self) -> &str {
+        &self.start_fen
+    }
+
+    // the moves played so far, without notation and undo information
+    pub fn moves(&self) -> impl Iterator<Item = Move> + '_ {
+        self.move_history.iter().map(|game_move| game_move.info.as_move())
     }
 
     pub fn move_history(&self) -> &[GameMove] {
@@ -37,15 +146,58 @@ impl GameState {
         self.move_history.last().map(|mv| mv.info).unwrap_or_default()
     }
 
-    pub fn play_move(&mut self, from: Square, to: Square) -> Option<MoveInfo> {
-        let mv = Move { from, to };
+    pub fn outcome(&self) -> GameResult {
+        let pos = &self.position;
+        let side_to_move = pos.side_to_move();
+        if !pos.has_legal_moves() {
+            if pos.king_in_check(side_to_move) {
+                GameResult::Checkmate(!side_to_move)
+            } else {
+                GameResult::Stalemate
+            }
+        } else if is_insufficient_material(pos) {
+            GameResult::InsufficientMaterial
+        } else if pos.halfmove_clock() >= 100 {
+            GameResult::FiftyMoveRule
+        } else if self.repetitions() >= 3 {
+            GameResult::ThreefoldRepetition
+        } else {
+            GameResult::Ongoing
+        }
+    }
+
+    // how many times the current position has occurred in the game, including now
+    pub fn repetitions(&self) -> usize {
+        let key = self.position.key();
+        // captures and pawn moves can't be undone, nothing before the last one can repeat
+        let reversible = self.position.halfmove_clock().max(0) as usize;
+        let earlier = self
+            .move_history
+            .iter()
+            .rev()
+            .take(reversible)
+            .filter(|game_move| game_move.info.key == key)
+            .count();
+        earlier + 1
+    }
+
+    pub fn play_move(&mut self, mv: Move) -> Option<MoveInfo> {
+        if self.outcome().is_over() {
+            return None;
+        }
+        self.replay_move(mv)
+    }
+
+    // Plays any legal move whatever the outcome, for games that were played elsewhere and are only followed here
+    pub fn replay_move(&mut self, mv: Move) -> Option<MoveInfo> {
         if !self.position.is_legal_move(mv.from, mv.to) {
             return None;
         }
 
-        let move_preview = MoveInfo::new(&self.position, mv.from, mv.to);
+        let move_preview = MoveInfo::new(&self.position, mv);
         let notation = algebraic_notation(&move_preview, &self.position);
         let move_info = self.position.move_piece(mv, true)?;
+        self.position.compute_legal_moves();
         self.move_history.push(GameMove {
             info: move_info,
             notation,
@@ -57,6 +209,7 @@ impl GameState {
     pub fn undo_move(&mut self) -> bool {
         if let Some(last_move) = self.move_history.pop() {
             self.position.undo_move(last_move.info);
+            self.position.compute_legal_moves();
             self.redo_history.push(last_move);
             true
         } else {
@@ -66,11 +219,8 @@ impl GameState {
 
     pub fn redo_move(&mut self) -> bool {
         if let Some(last_move) = self.redo_history.pop() {
-            let mv = Move {
-                from: last_move.info.from,
-                to: last_move.info.to,
-            };
-            if let Some(move_info) = self.position.move_piece(mv, true) {
+            if let Some(move_info) = self.position.move_piece(last_move.info.as_move(), true) {
+                self.position.compute_legal_moves();
                 self.move_history.push(GameMove {
                     info: move_info,
                     notation: last_move.notation,
@@ -86,11 +236,33 @@ impl GameState {
     }
 }
 
-fn algebraic_notation(info: &MoveInfo, position: &Position) -> String {
+// https://www.chessprogramming.org/Draw_Evaluation#Draws_by_Material
+// Only covers positions where no sequence of legal moves can mate: bare kings, a single minor piece, or bishops
+// that all stand on the same square color.
+fn is_insufficient_material(pos: &Position) -> bool {
+    let heavy_pieces = pos.piece_masks[PieceType::Pawn as usize]
+        | pos.piece_masks[PieceType::Rook as usize]
+        | pos.piece_masks[PieceType::Queen as usize];
+    if heavy_pieces != 0 {
+        return false;
+    }
+
+    let knights = pos.piece_masks[PieceType::Knight as usize];
+    let bishops = pos.piece_masks[PieceType::Bishop as usize];
+    if (knights | bishops).count_ones() <= 1 {
+        return true;
+    }
+
+    knights == 0 && (bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0)
+}
+
+// https://en.wikipedia.org/wiki/Algebraic_notation_(chess)
+// `position` is the one the move is played from. Check and mate are found by playing the move on a copy of it.
+pub fn algebraic_notation(info: &MoveInfo, position: &Position) -> String {
     let piece_identifier = disambiguate_move(info, position);
-    let to_square = format!("{:?}", info.to).to_lowercase();
+    let to_square = info.to.to_string();
 
-    match info.move_type {
+    let mut san = match info.move_type {
         MoveType::Quiet | MoveType::TwoSquarePush => [piece_identifier, to_square].join(""),
         MoveType::Capture | MoveType::EnPassant => [piece_identifier, to_square].join("x"),
         MoveType::Promotion => {
@@ -99,71 +271,70 @@ fn algebraic_notation(info: &MoveInfo, position: &Position) -> String {
                     "{}x{}={}",
                     Square::file_of(info.from).make_notation_string(),
                     to_square,
-                    PieceType::Queen.make_notation_string()
+                    info.promotion.make_notation_string()
                 )
             } else {
-                format!("{}={}", to_square, PieceType::Queen.make_notation_string())
+                format!("{}={}", to_square, info.promotion.make_notation_string())
             }
         }
         MoveType::Castle => {
-            if info.to == KINGSIDE_CASTLE_SQUARES[Piece::color_of(info.moved_piece) as usize] {
+            // the king captures its rook, which sits on the kingside exactly when it's right of the king
+            if Square::file_of(info.to) as u8 > Square::file_of(info.from) as u8 {
                 "O-O".into()
-            } else if info.to == QUEENSIDE_CASTLE_SQUARES[Piece::color_of(info.moved_piece) as usize] {
-                "O-O-O".into()
             } else {
-                "not handled".into()
+                "O-O-O".into()
             }
         }
-        MoveType::Invalid => "not handled".into(),
+        MoveType::Invalid => return "not handled".into(),
+    };
+    san.push_str(check_suffix(info, position));
+    san
+}
+
+fn check_suffix(info: &MoveInfo, position: &Position) -> &'static str {
+    // only a check needs the move played out to tell it from mate
+    let mv = info.as_move();
+    if !position.gives_check(mv) {
+        return "";
+    }
+    let mut after = position.clone();
+    if after.move_piece(mv, false).is_none() {
+        return "";
+    }
+    if after.has_legal_moves() {
+        "+"
+    } else {
+        "#"
     }
 }
 
+// Only the pieces of the same kind that can reach the same square have to be told apart. The file is preferred,
+// then the rank, then the whole square when neither is unique on its own.
 fn disambiguate_move(info: &MoveInfo, position: &Position) -> String {
     let piece_type = Piece::type_of(info.moved_piece);
-    let original_attack = position.bitboards.get_legal_moves(info.from);
-
-    let mut common_moves = original_attack;
-    let mut piece_sqs = vec![info.from];
-    for sq in Square::iter() {
-        let other_piece = position.board[sq as usize];
-        if (sq != info.from && Piece::color_of(info.moved_piece) == Piece::color_of(other_piece))
-            && (Piece::type_of(info.moved_piece) == Piece::type_of(other_piece))
-        {
-            let other_attack = position.bitboards.get_legal_moves(sq);
-            let check_common_moves = original_attack & other_attack;
-            if check_common_moves != 0 {
-                piece_sqs.push(sq);
-            }
-            common_moves &= other_attack;
-        }
+    let from_file = Square::file_of(info.from);
+    let from_rank = Square::rank_of(info.from);
+    if piece_type == PieceType::Pawn {
+        return if info.move_type == MoveType::EnPassant || info.move_type == MoveType::Capture {
+            from_file.make_notation_string().to_string()
+        } else {
+            String::new()
+        };
     }
 
-    if bitboards::is_bit_set(common_moves, info.to) && piece_sqs.len() > 1 {
-        let files = piece_sqs.iter().map(|&sq| Square::file_of(sq)).collect::<Vec<File>>();
-        let ranks = piece_sqs.iter().map(|&sq| Square::rank_of(sq)).collect::<Vec<Rank>>();
-        let files_are_same = files.iter().all(|&file| file == files[0]);
-        let ranks_are_same = ranks.iter().all(|&rank| rank == ranks[0]);
-        if !files_are_same {
-            format!(
-                "{}{}",
-                piece_type.make_notation_string(),
-                Square::file_of(info.from).make_notation_string()
-            )
-        } else if !ranks_are_same {
-            format!(
-                "{}{}",
-                piece_type.make_notation_string(),
-                Square::rank_of(info.from).make_notation_string()
-            )
-        } else {
-            let from = format!("{:?}", info.from).to_lowercase();
-            format!("{}{}", piece_type.make_notation_string(), from)
-        }
-    } else if piece_type == PieceType::Pawn
-        && (info.move_type == MoveType::EnPassant || info.move_type == MoveType::Capture)
-    {
-        Square::file_of(info.from).make_notation_string().to_string()
+    let rivals = Square::iter()
+        .filter(|&sq| sq != info.from && position.board[sq as usize] == info.moved_piece)
+        .filter(|&sq| position.is_legal_move(sq, info.to))
+        .collect::<Vec<_>>();
+
+    let piece = piece_type.make_notation_string();
+    if rivals.is_empty() {
+        piece.to_string()
+    } else if rivals.iter().all(|&sq| Square::file_of(sq) != from_file) {
+        format!("{}{}", piece, from_file.make_notation_string())
+    } else if rivals.iter().all(|&sq| Square::rank_of(sq) != from_rank) {
+        format!("{}{}", piece, from_rank.make_notation_string())
     } else {
-        piece_type.make_notation_string().to_string()
+        format!("{}{}", piece, info.from)
     }
 }
@@ -1,6 +1,14 @@
 pub mod bitboards;
+pub mod chess960;
+pub mod eval;
 pub mod game;
 pub mod moves;
+pub mod ordering;
+pub mod pgn;
 pub mod position;
+pub mod search;
+pub mod transposition;
+pub mod uci;
 pub mod ui;
 pub mod utils;
+pub mod zobrist;
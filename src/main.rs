@@ -2,25 +2,113 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use oracle::bitboards;
+use oracle::eval;
+use oracle::moves;
+use oracle::position::Position;
+use oracle::transposition::PerftTable;
+use oracle::uci;
 use oracle::ui;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::error::Error;
+use std::io;
+use std::time::Instant;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Cli {
     #[arg(long)]
     gen_magics: bool,
+
+    /// Speak the Universal Chess Interface over stdin/stdout instead of opening the GUI
+    #[arg(long)]
+    uci: bool,
+
+    /// Print a per-term evaluation breakdown of the given FEN and exit
+    #[arg(long, value_name = "FEN")]
+    eval: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Count the leaf nodes of the legal move tree, split by root move
+    Perft {
+        /// Depth in plies
+        depth: u32,
+
+        /// Position to count from, the starting position by default
+        fen: Option<String>,
+
+        /// Reuse the counts of transposed subtrees from a table of this size in megabytes
+        #[arg(long, value_name = "MB")]
+        hash: Option<usize>,
+
+        /// Count the root moves on this many threads
+        #[arg(long, default_value_t = 1, conflicts_with = "hash")]
+        threads: usize,
+    },
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Cli::parse();
-    if args.gen_magics {
+    if let Some(Command::Perft {
+        depth,
+        fen,
+        hash,
+        threads,
+    }) = args.command
+    {
+        bitboards::tables::force_load(&bitboards::LOOKUP_TABLES);
+        let mut position = Position::from_fen(fen.as_deref().unwrap_or(uci::START_FEN))?;
+        let mut table = hash.map(PerftTable::new);
+        perft(&mut position, depth, table.as_mut(), threads);
+    } else if args.gen_magics {
         bitboards::tables::compute()?;
+    } else if args.uci {
+        bitboards::tables::force_load(&bitboards::LOOKUP_TABLES);
+        uci::run(io::stdin().lock(), io::stdout())?;
+    } else if let Some(fen) = args.eval {
+        let mut position = Position::from_fen(&fen)?;
+        position.compute_legal_moves();
+        println!("{}", eval::evaluate_breakdown(&position));
     } else {
         bitboards::tables::force_load(&bitboards::LOOKUP_TABLES);
         ui::run_application()?;
     }
     Ok(())
 }
+
+// Divide output in the same format as Stockfish's `go perft` so the two can be diffed
+fn perft(position: &mut Position, depth: u32, mut table: Option<&mut PerftTable>, threads: usize) {
+    let start = Instant::now();
+    let divide = match table.as_deref_mut() {
+        Some(table) => moves::perft_divide_hashed(position, depth, table),
+        None if threads > 1 => moves::perft_divide_parallel(position, depth, threads),
+        None => moves::perft_divide(position, depth),
+    };
+    let elapsed = start.elapsed();
+
+    let mut nodes = 0;
+    for (mv, count) in divide {
+        println!("{}: {}", position.format_uci_move(mv), count);
+        nodes += count;
+    }
+    // perft 0 counts the position itself
+    if depth == 0 {
+        nodes = 1;
+    }
+
+    println!();
+    println!("Nodes searched: {}", nodes);
+    println!("Time: {:?}", elapsed);
+    println!(
+        "Nodes/second: {:.0}",
+        nodes as f64 / elapsed.as_secs_f64().max(f64::EPSILON)
+    );
+    if let Some(table) = table {
+        println!("Hash: {}", table.stats());
+    }
+}
@@ -2,95 +2,200 @@ use crate::bitboards::{self, Bitboard, LOOKUP_TABLES};
 use crate::position::Position;
 use crate::utils::{CastlingRights, Color, Direction, Piece, PieceType, Rank, Square};
 use num_traits::FromPrimitive;
-use std::ops::BitOr;
 
-pub const KINGSIDE_CASTLE_SQUARES: [Square; Color::Both as usize] = [Square::G1, Square::G8];
-pub const QUEENSIDE_CASTLE_SQUARES: [Square; Color::Both as usize] = [Square::C1, Square::C8];
+/*
+https://www.chessprogramming.org/Castling
+https://www.chessprogramming.org/Chess960
 
-const KINGSIDE_CASTLE_MASKS: [Bitboard; Color::Both as usize] = [
-    0b01110000,       // white back rank
-    0b01110000 << 56, // black back rank
-];
-const QUEENSIDE_CASTLE_MASKS: [Bitboard; Color::Both as usize] = [
-    0b00011110,       // white back rank
-    0b00011110 << 56, // black back rank
+King and rook end up on the same squares in standard chess and Chess960, only their start squares differ. Castling
+is encoded as the king capturing its own rook, which stays unambiguous when the king only moves one square or not at
+all.
+*/
+
+// indices into `Position::castling_rooks`
+pub const KINGSIDE: usize = 0;
+pub const QUEENSIDE: usize = 1;
+
+pub const CASTLING_RIGHTS: [[CastlingRights; 2]; Color::Both as usize] = [
+    [CastlingRights::WhiteOO, CastlingRights::WhiteOOO],
+    [CastlingRights::BlackOO, CastlingRights::BlackOOO],
 ];
 
-#[derive(Default)]
-pub struct ComputedMoves {
-    pub legal_moves: Bitboard,
-    pub attacks: Bitboard,
+// king destinations
+pub const KINGSIDE_CASTLE_SQUARES: [Square; Color::Both as usize] = [Square::G1, Square::G8];
+pub const QUEENSIDE_CASTLE_SQUARES: [Square; Color::Both as usize] = [Square::C1, Square::C8];
+
+// rook destinations
+pub const KINGSIDE_ROOK_SQUARES: [Square; Color::Both as usize] = [Square::F1, Square::F8];
+pub const QUEENSIDE_ROOK_SQUARES: [Square; Color::Both as usize] = [Square::D1, Square::D8];
+
+// Where king and rook land when `color` castles to `side`
+pub fn castling_destinations(color: Color, side: usize) -> (Square, Square) {
+    if side == KINGSIDE {
+        (
+            KINGSIDE_CASTLE_SQUARES[color as usize],
+            KINGSIDE_ROOK_SQUARES[color as usize],
+        )
+    } else {
+        (
+            QUEENSIDE_CASTLE_SQUARES[color as usize],
+            QUEENSIDE_ROOK_SQUARES[color as usize],
+        )
+    }
 }
 
-impl BitOr for ComputedMoves {
-    type Output = ComputedMoves;
+// Calls `visit` with every piece of the side to move and the bitboard of its legal destinations. Moves are derived
+// from the pin and check masks directly, the only attack map computed is the enemy one needed for king moves.
+pub fn for_each_legal_move_set(pos: &Position, mut visit: impl FnMut(Square, Bitboard)) {
+    let color = pos.side_to_move;
+    let king_sq = pos.king_squares[color as usize];
+    let friendly_pieces = pos.bitboards.get_checkers(color);
+    let occupancy = pos.bitboards.get_checkers(Color::Both);
+
+    // For king safety, enemy sliders must see through the friendly king's
+    // current square. Otherwise a king could illegally move along a rook,
+    // bishop, or queen ray that it was previously blocking.
+    let enemy_attacks = attacks_by(pos, !color, bitboards::clear_bit(occupancy, king_sq));
+    visit(king_sq, compute_king_moves(pos, color, enemy_attacks));
 
-    fn bitor(self, rhs: ComputedMoves) -> ComputedMoves {
-        ComputedMoves {
-            legal_moves: self.legal_moves | rhs.legal_moves,
-            attacks: self.attacks | rhs.attacks,
-        }
+    let (pinned_masks, check_mask) = compute_pin_and_check_masks(pos, color);
+    if check_mask == 0 {
+        // double check, only the king can move
+        return;
+    }
+
+    let mut pieces = bitboards::clear_bit(friendly_pieces, king_sq);
+    while pieces != 0 {
+        let sq = Square::from_u8(pieces.trailing_zeros() as u8).unwrap_or_default();
+        pieces &= pieces - 1;
+
+        let piece_type = Piece::type_of(pos.board[sq as usize]);
+        let mut evasion_mask = check_mask;
+        let targets = match piece_type {
+            PieceType::Pawn => {
+                if pos.en_passant_sq != Square::Count {
+                    // capturing the checking pawn en passant lands on a square outside the check mask
+                    let double_pushed_sq = pos.en_passant_sq + Direction::forward_direction(!color);
+                    if bitboards::is_bit_set(check_mask, double_pushed_sq) {
+                        evasion_mask = bitboards::set_bit(evasion_mask, pos.en_passant_sq);
+                    }
+                }
+                compute_pawn_moves(pos, sq, color)
+            }
+            PieceType::Knight => compute_knight_moves(sq),
+            PieceType::Rook => compute_rook_moves(occupancy, sq),
+            PieceType::Bishop => compute_bishop_moves(occupancy, sq),
+            PieceType::Queen => compute_rook_moves(occupancy, sq) | compute_bishop_moves(occupancy, sq),
+            _ => 0,
+        };
+
+        // can't capture own pieces
+        visit(
+            sq,
+            targets & !friendly_pieces & evasion_mask & pinned_masks[sq as usize],
+        );
     }
 }
 
+// Fills the per-square legal move cache used by the UI and `Position::is_legal_move`
 pub fn compute_legal_moves(pos: &mut Position) {
-    let friendly_color = pos.side_to_move;
-    let (pinned_masks, check_mask) = compute_pin_and_check_masks(pos, friendly_color);
-
-    let compute_moves_for_color = |pos: &mut Position, color: Color| {
-        let mut attacks = Bitboard::default();
-
-        // For king safety, enemy sliders must see through the friendly king's
-        // current square. Otherwise a king could illegally move along a rook,
-        // bishop, or queen ray that it was previously blocking.
-        let attack_occupancy = if color != friendly_color {
-            let full_checkers_bb = pos.bitboards.get_checkers(Color::Both);
-            bitboards::clear_bit(full_checkers_bb, pos.king_squares[friendly_color as usize])
-        } else {
-            pos.bitboards.get_checkers(Color::Both)
-        };
+    let mut legal_moves = [0; Square::Count as usize];
+    for_each_legal_move_set(pos, |sq, targets| legal_moves[sq as usize] = targets);
+    for sq in Square::iter() {
+        pos.bitboards.set_legal_moves(sq, legal_moves[sq as usize]);
+    }
+}
 
-        let mut pieces = pos.bitboards.get_checkers(color);
-        while pieces != 0 {
-            let sq = Square::from_u8(pieces.trailing_zeros() as u8).unwrap_or_default();
-            pieces &= pieces - 1;
-
-            let piece = pos.board[sq as usize];
-            let piece_type = Piece::type_of(piece);
-            let piece_color = Piece::color_of(piece);
-
-            // psuedo-legal moves
-            let mut computed_moves = match piece_type {
-                PieceType::Pawn => compute_pawn_moves(pos, sq, piece_color),
-                PieceType::Knight => compute_knight_moves(sq),
-                PieceType::Rook => compute_rook_moves(attack_occupancy, sq),
-                PieceType::Bishop => compute_bishop_moves(attack_occupancy, sq),
-                PieceType::Queen => {
-                    compute_rook_moves(attack_occupancy, sq) | compute_bishop_moves(attack_occupancy, sq)
-                }
-                PieceType::King => compute_king_moves(pos, color),
-                _ => ComputedMoves::default(),
-            };
+// Legal destinations of a single piece without touching the cache
+pub fn legal_destinations_from(pos: &Position, from: Square) -> Bitboard {
+    let mut destinations = 0;
+    for_each_legal_move_set(pos, |sq, targets| {
+        if sq == from {
+            destinations = targets;
+        }
+    });
+    destinations
+}
 
-            // can't capture own pieces
-            computed_moves.legal_moves &= !pos.bitboards.get_checkers(piece_color);
+// Whether the side to move has a legal move, in one pass over its pieces without touching the cache
+pub fn has_legal_moves(pos: &Position) -> bool {
+    let mut found = false;
+    for_each_legal_move_set(pos, |_, targets| found |= targets != 0);
+    found
+}
 
-            if color == friendly_color && piece_type != PieceType::King {
-                computed_moves.legal_moves &= check_mask;
-                computed_moves.legal_moves &= pinned_masks[sq as usize];
-            }
+// Every square attacked by `color`, with sliders blocked by `occupancy`
+pub fn attacks_by(pos: &Position, color: Color, occupancy: Bitboard) -> Bitboard {
+    let mut attacks = 0;
+    let mut pieces = pos.bitboards.get_checkers(color);
+    while pieces != 0 {
+        let sq = Square::from_u8(pieces.trailing_zeros() as u8).unwrap_or_default();
+        pieces &= pieces - 1;
 
-            pos.bitboards.set_legal_moves(sq, computed_moves.legal_moves);
-            attacks |= computed_moves.attacks;
-        }
-        pos.bitboards.set_attacks(color, attacks);
-    };
+        attacks |= attacks_from(Piece::type_of(pos.board[sq as usize]), color, sq, occupancy);
+    }
+    attacks
+}
+
+// Squares a `color` piece of `piece_type` on `sq` attacks, with sliders blocked by `occupancy`
+pub fn attacks_from(piece_type: PieceType, color: Color, sq: Square, occupancy: Bitboard) -> Bitboard {
+    match piece_type {
+        PieceType::Pawn => LOOKUP_TABLES.get_pawn_attack_mask(color, sq),
+        PieceType::Knight => compute_knight_moves(sq),
+        PieceType::Rook => compute_rook_moves(occupancy, sq),
+        PieceType::Bishop => compute_bishop_moves(occupancy, sq),
+        PieceType::Queen => compute_rook_moves(occupancy, sq) | compute_bishop_moves(occupancy, sq),
+        PieceType::King => LOOKUP_TABLES.get_king_mask(sq),
+        PieceType::Empty => 0,
+    }
+}
+
+// Pieces of both colors within `occupancy` that attack `sq`, with sliders blocked by `occupancy`. Taking pieces out of
+// the occupancy uncovers the sliders behind them.
+pub fn attackers_to(pos: &Position, sq: Square, occupancy: Bitboard) -> Bitboard {
+    let masks = &pos.piece_masks;
+    let rooks_queens = masks[PieceType::Rook as usize] | masks[PieceType::Queen as usize];
+    let bishops_queens = masks[PieceType::Bishop as usize] | masks[PieceType::Queen as usize];
+
+    // a pawn attacks `sq` from wherever a pawn of the other color on `sq` would attack
+    let pawns = (LOOKUP_TABLES.get_pawn_attack_mask(Color::Black, sq) & pos.bitboards.get_checkers(Color::White)
+        | LOOKUP_TABLES.get_pawn_attack_mask(Color::White, sq) & pos.bitboards.get_checkers(Color::Black))
+        & masks[PieceType::Pawn as usize];
+    let attackers = pawns
+        | compute_knight_moves(sq) & masks[PieceType::Knight as usize]
+        | LOOKUP_TABLES.get_king_mask(sq) & masks[PieceType::King as usize]
+        | compute_rook_moves(occupancy, sq) & rooks_queens
+        | compute_bishop_moves(occupancy, sq) & bishops_queens;
+    attackers & occupancy
+}
+
+// Looks outwards from `sq` instead of building the whole attack map, which is much cheaper for a single square
+pub fn is_square_attacked(pos: &Position, sq: Square, by: Color) -> bool {
+    is_square_attacked_through(pos, sq, by, pos.bitboards.get_checkers(Color::Both))
+}
 
-    compute_moves_for_color(pos, !pos.side_to_move); // enemy moves first
-    compute_moves_for_color(pos, pos.side_to_move); // then friendly moves to handle checks/pins
+// Like `is_square_attacked`, with sliders blocked by `occupancy` instead of the pieces on the board
+fn is_square_attacked_through(pos: &Position, sq: Square, by: Color, occupancy: Bitboard) -> bool {
+    let attackers = pos.bitboards.get_checkers(by);
+    let queens = pos.piece_masks[PieceType::Queen as usize];
+
+    // a pawn of `by` attacks `sq` from wherever a pawn of the other color on `sq` would attack
+    LOOKUP_TABLES.get_pawn_attack_mask(!by, sq) & attackers & pos.piece_masks[PieceType::Pawn as usize] != 0
+        || compute_knight_moves(sq) & attackers & pos.piece_masks[PieceType::Knight as usize] != 0
+        || LOOKUP_TABLES.get_king_mask(sq) & attackers & pos.piece_masks[PieceType::King as usize] != 0
+        || compute_rook_moves(occupancy, sq) & attackers & (pos.piece_masks[PieceType::Rook as usize] | queens) != 0
+        || compute_bishop_moves(occupancy, sq) & attackers & (pos.piece_masks[PieceType::Bishop as usize] | queens) != 0
 }
 
-fn compute_pawn_moves(pos: &Position, sq: Square, color: Color) -> ComputedMoves {
+// Pieces of `color` that may only move along the line between their king and the enemy slider pinning them
+pub fn pinned_pieces(pos: &Position, color: Color) -> Bitboard {
+    let (pin_masks, _) = compute_pin_and_check_masks(pos, color);
+    Square::iter()
+        .filter(|&sq| pin_masks[sq as usize] != u64::MAX)
+        .fold(0, bitboards::set_bit)
+}
+
+fn compute_pawn_moves(pos: &Position, sq: Square, color: Color) -> Bitboard {
     let mut legal_moves = 0;
     let forward = Direction::forward_direction(color);
     let start_rank = Rank::relative_rank(color, Square::rank_of(sq));
@@ -116,83 +221,100 @@ fn compute_pawn_moves(pos: &Position, sq: Square, color: Color) -> ComputedMoves
         0
     };
 
-    legal_moves |= attack_mask & (enemy_checkers | en_passant_bit);
-
-    ComputedMoves {
-        legal_moves,
-        attacks: attack_mask,
+    legal_moves |= attack_mask & enemy_checkers;
+    if attack_mask & en_passant_bit != 0 && !en_passant_exposes_king(pos, sq, color) {
+        legal_moves |= en_passant_bit;
     }
+
+    legal_moves
 }
-fn compute_knight_moves(sq: Square) -> ComputedMoves {
-    let attacks = LOOKUP_TABLES.get_knight_mask(sq);
-    ComputedMoves {
-        attacks,
-        legal_moves: attacks,
+
+// En passant removes two pawns from the same rank at once, which the pin masks don't account for: with the king and
+// an enemy rook or queen on that rank, taking can expose the king. Rare enough to simply test the resulting position.
+fn en_passant_exposes_king(pos: &Position, from: Square, color: Color) -> bool {
+    let king_sq = pos.king_squares[color as usize];
+    if king_sq == Square::Count {
+        return false;
     }
+
+    let captured_sq = pos.en_passant_sq + Direction::forward_direction(!color);
+    let occupancy = bitboards::set_bit(
+        bitboards::clear_bit(
+            bitboards::clear_bit(pos.bitboards.get_checkers(Color::Both), from),
+            captured_sq,
+        ),
+        pos.en_passant_sq,
+    );
+
+    let enemy_pieces = pos.bitboards.get_checkers(!color);
+    let queens = pos.piece_masks[PieceType::Queen as usize];
+    let enemy_rook_queens = enemy_pieces & (pos.piece_masks[PieceType::Rook as usize] | queens);
+    let enemy_bishop_queens = enemy_pieces & (pos.piece_masks[PieceType::Bishop as usize] | queens);
+
+    compute_rook_moves(occupancy, king_sq) & enemy_rook_queens != 0
+        || compute_bishop_moves(occupancy, king_sq) & enemy_bishop_queens != 0
+}
+
+fn compute_knight_moves(sq: Square) -> Bitboard {
+    LOOKUP_TABLES.get_knight_mask(sq)
 }
 
-fn compute_rook_moves(occupancy: Bitboard, sq: Square) -> ComputedMoves {
+fn compute_rook_moves(occupancy: Bitboard, sq: Square) -> Bitboard {
     let move_mask = LOOKUP_TABLES.get_orthogonal_mask(sq);
     let blocker_key = occupancy & move_mask;
-    let attacks = LOOKUP_TABLES.get_rook_mask(sq, blocker_key);
-
-    ComputedMoves {
-        attacks,
-        legal_moves: attacks,
-    }
+    LOOKUP_TABLES.get_rook_mask(sq, blocker_key)
 }
 
-fn compute_bishop_moves(occupancy: Bitboard, sq: Square) -> ComputedMoves {
+fn compute_bishop_moves(occupancy: Bitboard, sq: Square) -> Bitboard {
     let diagonal_mask = LOOKUP_TABLES.get_diagonal_mask(sq);
     let blocker_key = occupancy & diagonal_mask;
-    let attacks = LOOKUP_TABLES.get_bishop_mask(sq, blocker_key);
-
-    ComputedMoves {
-        legal_moves: attacks,
-        attacks,
-    }
+    LOOKUP_TABLES.get_bishop_mask(sq, blocker_key)
 }
 
-fn compute_king_moves(pos: &Position, color: Color) -> ComputedMoves {
+fn compute_king_moves(pos: &Position, color: Color, enemy_attacks: Bitboard) -> Bitboard {
     let sq = pos.king_squares[color as usize];
-    let enemy_attacks = pos.bitboards.get_attacks(!color);
     let friendly_pieces = pos.bitboards.get_checkers(color);
 
     let attacks = LOOKUP_TABLES.get_king_mask(sq);
     let mut legal_moves = attacks & !friendly_pieces & !enemy_attacks;
 
-    if pos.castling_rights != CastlingRights::NoCastling {
-        // use this wiki for test cases: https://en.wikipedia.org/wiki/Castling
-        let friendly_pieces_no_king = bitboards::clear_bit(friendly_pieces, sq);
+    if pos.castling_rights == CastlingRights::NoCastling {
+        return legal_moves;
+    }
 
-        let kingside_castle_mask = KINGSIDE_CASTLE_MASKS[color as usize];
-        let kingside_castle_sq = KINGSIDE_CASTLE_SQUARES[color as usize];
-        let kingside_rights_mask = if color == Color::White {
-            CastlingRights::WhiteOO
-        } else {
-            CastlingRights::BlackOO
-        };
-        let kingside_rights = pos.castling_rights & kingside_rights_mask;
-        let kingside_blockers = kingside_castle_mask & (friendly_pieces_no_king | enemy_attacks);
-        if (kingside_rights != CastlingRights::NoCastling) && (kingside_blockers == 0) {
-            legal_moves = bitboards::set_bit(legal_moves, kingside_castle_sq);
+    // use this wiki for test cases: https://en.wikipedia.org/wiki/Castling
+    for side in [KINGSIDE, QUEENSIDE] {
+        if pos.castling_rights & CASTLING_RIGHTS[color as usize][side] == CastlingRights::NoCastling {
+            continue;
+        }
+        let rook_sq = pos.castling_rooks[color as usize][side];
+        let (king_to, rook_to) = castling_destinations(color, side);
+
+        // every square either piece crosses or lands on has to be empty, not counting the two of them. The rook is
+        // also taken off for the attack test, in Chess960 it can shield the king's path along the back rank.
+        let occupancy = bitboards::clear_bit(
+            bitboards::clear_bit(pos.bitboards.get_checkers(Color::Both), sq),
+            rook_sq,
+        );
+        let king_path = squares_spanned(sq, king_to);
+        if (king_path | squares_spanned(rook_sq, rook_to)) & occupancy != 0 {
+            continue;
         }
 
-        let queenside_castle_mask = QUEENSIDE_CASTLE_MASKS[color as usize];
-        let queenside_castle_sq = QUEENSIDE_CASTLE_SQUARES[color as usize];
-        let queenside_rights_mask = if color == Color::White {
-            CastlingRights::WhiteOOO
-        } else {
-            CastlingRights::BlackOOO
-        };
-        let queenside_rights = pos.castling_rights & queenside_rights_mask;
-        let queenside_blockers = queenside_castle_mask & (friendly_pieces_no_king | enemy_attacks);
-        if (queenside_rights != CastlingRights::NoCastling) && (queenside_blockers == 0) {
-            legal_moves = bitboards::set_bit(legal_moves, queenside_castle_sq);
+        // no castling out of, through or into check
+        let mut path = king_path;
+        let mut safe = true;
+        while path != 0 && safe {
+            let path_sq = Square::from_u8(path.trailing_zeros() as u8).unwrap_or_default();
+            path &= path - 1;
+            safe = !is_square_attacked_through(pos, path_sq, !color, occupancy);
+        }
+        if safe {
+            legal_moves = bitboards::set_bit(legal_moves, rook_sq);
         }
     }
 
-    ComputedMoves { attacks, legal_moves }
+    legal_moves
 }
 
 fn compute_pin_and_check_masks(pos: &Position, color: Color) -> ([Bitboard; Square::Count as usize], Bitboard) {
@@ -222,10 +344,10 @@ fn compute_pin_and_check_masks(pos: &Position, color: Color) -> ([Bitboard; Squa
         let pinner_sq = Square::from_u8(pinner_idx as u8).unwrap_or_default();
         let mut pinner_attacks = 0;
         if bitboards::is_bit_set(enemy_rook_queens, pinner_sq) {
-            pinner_attacks |= compute_rook_moves(occupancy, pinner_sq).attacks;
+            pinner_attacks |= compute_rook_moves(occupancy, pinner_sq);
         }
         if bitboards::is_bit_set(enemy_bishop_queens, pinner_sq) {
-            pinner_attacks |= compute_bishop_moves(occupancy, pinner_sq).attacks;
+            pinner_attacks |= compute_bishop_moves(occupancy, pinner_sq);
         }
 
         let between = squares_between(king_sq, pinner_sq);
@@ -241,10 +363,10 @@ fn compute_pin_and_check_masks(pos: &Position, color: Color) -> ([Bitboard; Squa
             let xray_occupancy = occupancy & !blockers;
             let mut xray_attacks = 0;
             if bitboards::is_bit_set(enemy_rook_queens, pinner_sq) {
-                xray_attacks |= compute_rook_moves(xray_occupancy, pinner_sq).attacks;
+                xray_attacks |= compute_rook_moves(xray_occupancy, pinner_sq);
             }
             if bitboards::is_bit_set(enemy_bishop_queens, pinner_sq) {
-                xray_attacks |= compute_bishop_moves(xray_occupancy, pinner_sq).attacks;
+                xray_attacks |= compute_bishop_moves(xray_occupancy, pinner_sq);
             }
 
             if (xray_attacks & king_bit) != 0 {
@@ -310,3 +432,8 @@ fn squares_between(a: Square, b: Square) -> Bitboard {
 
     between
 }
+
+// `a`, `b` and everything between them on a line
+fn squares_spanned(a: Square, b: Square) -> Bitboard {
+    bitboards::set_bit(bitboards::set_bit(squares_between(a, b), a), b)
+}
@@ -1,19 +1,25 @@
-use super::info::{Move, MoveList};
+use super::compute;
+use super::info::{Move, MoveList, PROMOTION_PIECES};
+use crate::bitboards::{self, Bitboard};
 use crate::position::Position;
-use crate::utils::Square;
+use crate::transposition::PerftTable;
+use crate::utils::{Piece, PieceType, Rank, Square};
 use num_traits::FromPrimitive;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+const BACK_RANKS: Bitboard = 0xff00_0000_0000_00ff;
 
 pub fn count_legal_moves(pos: &mut Position, ply: u32) -> u64 {
     if ply == 0 {
         return 1;
     }
+    if ply == 1 {
+        return count_moves(pos);
+    }
 
-    pos.compute_legal_moves();
     let mut moves = MoveList::default();
     generate_moves(pos, &mut moves);
-    if ply == 1 {
-        return moves.len() as u64;
-    }
 
     let mut nodes = 0;
     for mv in moves.iter() {
@@ -27,18 +33,169 @@ pub fn count_legal_moves(pos: &mut Position, ply: u32) -> u64 {
     nodes
 }
 
+// Subtrees reached by transposition are looked up instead of counted again. Entries are keyed by position and
+// only reused for the exact same remaining depth.
+pub fn count_legal_moves_hashed(pos: &mut Position, ply: u32, table: &mut PerftTable) -> u64 {
+    if ply <= 1 {
+        return count_legal_moves(pos, ply);
+    }
+
+    let key = pos.key();
+    if let Some(entry) = table.probe(key) {
+        if entry.depth as u32 == ply {
+            return entry.data;
+        }
+    }
+
+    let mut moves = MoveList::default();
+    generate_moves(pos, &mut moves);
+
+    let mut nodes = 0;
+    for mv in moves.iter() {
+        let undo = pos
+            .move_piece(mv, false)
+            .unwrap_or_else(|| panic!("generated invalid move: {:?} -> {:?}", mv.from, mv.to));
+        nodes += count_legal_moves_hashed(pos, ply - 1, table);
+        pos.undo_move(undo);
+    }
+
+    table.store(key, ply, nodes);
+    nodes
+}
+
+// https://www.chessprogramming.org/Perft#Divide
+// Node count below every root move, in generation order
+pub fn perft_divide(pos: &mut Position, ply: u32) -> Vec<(Move, u64)> {
+    divide(pos, ply, count_legal_moves)
+}
+
+pub fn perft_divide_hashed(pos: &mut Position, ply: u32, table: &mut PerftTable) -> Vec<(Move, u64)> {
+    divide(pos, ply, |pos, ply| count_legal_moves_hashed(pos, ply, table))
+}
+
+// Same counts as `perft_divide`, with the root moves handed out to `threads` workers that each count on their own
+// copy of the position
+pub fn perft_divide_parallel(pos: &Position, ply: u32, threads: usize) -> Vec<(Move, u64)> {
+    if ply == 0 {
+        return Vec::new();
+    }
+
+    let mut moves = MoveList::default();
+    generate_moves(pos, &mut moves);
+    let next_move = AtomicUsize::new(0);
+    let mut counts = vec![0; moves.len()];
+
+    thread::scope(|scope| {
+        let workers = (0..threads.clamp(1, moves.len().max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut pos = pos.clone();
+                    let mut counted = Vec::new();
+                    loop {
+                        let idx = next_move.fetch_add(1, Ordering::Relaxed);
+                        if idx >= moves.len() {
+                            break counted;
+                        }
+
+                        let mv = moves.moves[idx];
+                        let undo = pos
+                            .move_piece(mv, false)
+                            .unwrap_or_else(|| panic!("generated invalid move: {:?} -> {:?}", mv.from, mv.to));
+                        counted.push((idx, count_legal_moves(&mut pos, ply - 1)));
+                        pos.undo_move(undo);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for worker in workers {
+            for (idx, count) in worker.join().expect("perft worker panicked") {
+                counts[idx] = count;
+            }
+        }
+    });
+
+    moves.iter().zip(counts).collect()
+}
+
+pub fn count_legal_moves_parallel(pos: &Position, ply: u32, threads: usize) -> u64 {
+    if ply <= 1 {
+        return count_legal_moves(&mut pos.clone(), ply);
+    }
+    perft_divide_parallel(pos, ply, threads)
+        .iter()
+        .map(|&(_, count)| count)
+        .sum()
+}
+
+fn divide(pos: &mut Position, ply: u32, mut count: impl FnMut(&mut Position, u32) -> u64) -> Vec<(Move, u64)> {
+    if ply == 0 {
+        return Vec::new();
+    }
+
+    let mut moves = MoveList::default();
+    generate_moves(pos, &mut moves);
+
+    let mut divide = Vec::with_capacity(moves.len());
+    for mv in moves.iter() {
+        let undo = pos
+            .move_piece(mv, false)
+            .unwrap_or_else(|| panic!("generated invalid move: {:?} -> {:?}", mv.from, mv.to));
+        divide.push((mv, count(pos, ply - 1)));
+        pos.undo_move(undo);
+    }
+    divide
+}
+
+// Bulk counting at the leaves, the moves themselves are never needed there
+fn count_moves(pos: &Position) -> u64 {
+    let mut count = 0;
+    compute::for_each_legal_move_set(pos, |sq, targets| {
+        count += targets.count_ones() as u64;
+        if Piece::type_of(pos.board[sq as usize]) == PieceType::Pawn {
+            // every promotion is four moves
+            count += (targets & BACK_RANKS).count_ones() as u64 * (PROMOTION_PIECES.len() as u64 - 1);
+        }
+    });
+    count
+}
+
 pub fn generate_moves(pos: &Position, out: &mut MoveList) {
     out.clear();
-    let mut pieces = pos.bitboards.get_checkers(pos.side_to_move());
-    while pieces != 0 {
-        let sq = Square::from_u8(pieces.trailing_zeros() as u8).unwrap_or_default();
-        pieces &= pieces - 1;
-
-        let mut targets = pos.legal_destinations_from(sq);
-        while targets != 0 {
-            let to = Square::from_u8(targets.trailing_zeros() as u8).unwrap_or_default();
-            targets &= targets - 1;
-            out.push(Move { from: sq, to });
+    compute::for_each_legal_move_set(pos, |sq, targets| push_moves(pos, sq, targets, out));
+}
+
+// https://www.chessprogramming.org/Quiescence_Search
+// Legal captures, en passant and promotions only, quiet promotions included since they change the material balance
+// as much as a capture does
+pub fn generate_captures(pos: &Position, out: &mut MoveList) {
+    out.clear();
+    let enemy_pieces = pos.bitboards.get_checkers(!pos.side_to_move);
+    compute::for_each_legal_move_set(pos, |sq, targets| {
+        let mut captures = enemy_pieces;
+        if Piece::type_of(pos.board[sq as usize]) == PieceType::Pawn {
+            captures |= BACK_RANKS;
+            if pos.en_passant_sq != Square::Count {
+                captures = bitboards::set_bit(captures, pos.en_passant_sq);
+            }
+        }
+        push_moves(pos, sq, targets & captures, out);
+    });
+}
+
+fn push_moves(pos: &Position, sq: Square, mut targets: Bitboard, out: &mut MoveList) {
+    let is_pawn = Piece::type_of(pos.board[sq as usize]) == PieceType::Pawn;
+    while targets != 0 {
+        let to = Square::from_u8(targets.trailing_zeros() as u8).unwrap_or_default();
+        targets &= targets - 1;
+
+        let to_rank = Square::rank_of(to);
+        if is_pawn && (to_rank == Rank::Rank8 || to_rank == Rank::Rank1) {
+            for promotion in PROMOTION_PIECES {
+                out.push(Move::with_promotion(sq, to, promotion));
+            }
+        } else {
+            out.push(Move::new(sq, to));
         }
     }
 }
@@ -1,15 +1,44 @@
-use super::compute::{KINGSIDE_CASTLE_SQUARES, QUEENSIDE_CASTLE_SQUARES};
 use crate::position::Position;
 use crate::utils::{CastlingRights, Direction, MoveType, Piece, PieceType, Rank, Square};
 
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
 pub struct Move {
     pub from: Square,
     pub to: Square,
+    pub promotion: PieceType, // PieceType::Empty unless a pawn moves to the last rank
+}
+
+// in the order they are generated, most useful first
+pub const PROMOTION_PIECES: [PieceType; 4] = [PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight];
+
+impl Move {
+    pub fn new(from: Square, to: Square) -> Move {
+        Move {
+            from,
+            to,
+            promotion: PieceType::Empty,
+        }
+    }
+
+    pub fn with_promotion(from: Square, to: Square, promotion: PieceType) -> Move {
+        Move { from, to, promotion }
+    }
+
+    // Coordinate notation as used by UCI, e.g. "e2e4" or "e7e8q". A move that goes nowhere is the null move "0000".
+    pub fn to_uci(&self) -> String {
+        if self.from == self.to {
+            return "0000".into();
+        }
+        let promotion = match self.promotion {
+            PieceType::Empty => "".into(),
+            _ => self.promotion.make_notation_string().to_lowercase(),
+        };
+        format!("{}{}{}", self.from, self.to, promotion)
+    }
 }
 
 // Enough for reachable orthodox chess positions; arbitrary FENs can exceed this.
-const MAX_MOVES: usize = 256;
+pub const MAX_MOVES: usize = 256;
 
 #[derive(Clone, Copy, Debug)]
 pub struct MoveList {
@@ -58,17 +87,20 @@ pub struct MoveInfo {
     pub moved_piece: Piece,
     pub captured_piece: Piece,
     pub capture_piece_sq: Square,
+    pub promotion: PieceType,
     pub en_passant_sq: Square,
     pub castling_rights: CastlingRights,
     pub fullmove_count: i32,
     pub halfmove_clock: i32,
+    pub key: u64,
 }
 
 impl MoveInfo {
-    pub fn new(position: &Position, from: Square, to: Square) -> MoveInfo {
+    pub fn new(position: &Position, mv: Move) -> MoveInfo {
+        let Move { from, to, .. } = mv;
         let move_type;
+        let mut promotion = PieceType::Empty;
         let moved_piece = position.board[from as usize];
-        let moved_piece_color = Piece::color_of(moved_piece);
         let moved_piece_type = Piece::type_of(moved_piece);
 
         let mut captured_piece = position.board[to as usize];
@@ -87,6 +119,8 @@ impl MoveInfo {
                 if relative_from_rank == Rank::Rank7 {
                     move_type = MoveType::Promotion;
                     capture_piece_sq = to;
+                    // stays Empty when the move doesn't name a piece, `Position::move_piece` refuses to play that
+                    promotion = mv.promotion;
                 } else if relative_from_rank == Rank::Rank2 && relative_to_rank == Rank::Rank4 {
                     move_type = MoveType::TwoSquarePush;
                 } else if from_file != to_file {
@@ -103,13 +137,13 @@ impl MoveInfo {
                 }
             }
             PieceType::King => {
-                if captured_piece != Piece::Empty {
+                // castling is encoded as the king capturing its own rook
+                if position.castling_side(mv).is_some() {
+                    move_type = MoveType::Castle;
+                    captured_piece = Piece::Empty;
+                } else if captured_piece != Piece::Empty {
                     move_type = MoveType::Capture;
                     capture_piece_sq = to;
-                } else if to == KINGSIDE_CASTLE_SQUARES[moved_piece_color as usize]
-                    || to == QUEENSIDE_CASTLE_SQUARES[moved_piece_color as usize]
-                {
-                    move_type = MoveType::Castle;
                 } else {
                     move_type = MoveType::Quiet;
                 }
@@ -131,13 +165,19 @@ impl MoveInfo {
             moved_piece,
             captured_piece,
             capture_piece_sq,
+            promotion,
             en_passant_sq: position.en_passant_sq,
             castling_rights: position.castling_rights,
             fullmove_count: position.fullmove_count(),
             halfmove_clock: position.halfmove_clock(),
+            key: position.key(),
         }
     }
 
+    pub fn as_move(&self) -> Move {
+        Move::with_promotion(self.from, self.to, self.promotion)
+    }
+
     pub fn is_valid(&self) -> bool {
         self.move_type != MoveType::Invalid
     }
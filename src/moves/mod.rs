@@ -2,4 +2,7 @@ pub mod compute;
 pub mod generate;
 pub mod info;
 
-pub use generate::{count_legal_moves, generate_moves};
+pub use generate::{
+    count_legal_moves, count_legal_moves_hashed, count_legal_moves_parallel, generate_captures, generate_moves,
+    perft_divide, perft_divide_hashed, perft_divide_parallel,
+};
@@ -0,0 +1,208 @@
+/*
+https://www.chessprogramming.org/Move_Ordering
+https://www.chessprogramming.org/MVV-LVA
+https://www.chessprogramming.org/Killer_Heuristic
+https://www.chessprogramming.org/History_Heuristic
+
+Alpha-beta cuts off sooner the earlier the best move is tried. Moves are handed out in stages: the hash move, captures
+and promotions by most valuable victim / least valuable attacker, the killer moves that refuted a sibling at the same
+ply, then the remaining quiet moves by how often they caused a cutoff anywhere in the tree. Within a stage the best
+move is only picked when it's asked for, so a cutoff on the first few moves saves sorting the rest.
+*/
+
+use crate::eval::PIECE_VALUES;
+use crate::moves::info::{Move, MoveInfo, MoveList, MAX_MOVES};
+use crate::position::Position;
+use crate::search::MAX_PLY;
+use crate::utils::{Color, MoveType, Piece, Square};
+
+pub const MAX_KILLERS: usize = 2;
+
+// all history scores are halved once one of them gets here, recent cutoffs weigh more and nothing overflows
+const HISTORY_LIMIT: i32 = 1 << 20;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum Stage {
+    #[default]
+    HashMove,
+    Captures,
+    Killers,
+    Quiets,
+    Done,
+}
+
+// What the search learns about quiet moves, kept for the duration of one search
+#[derive(Clone)]
+pub struct OrderingTables {
+    killers: [[Option<Move>; MAX_KILLERS]; MAX_PLY as usize],
+    // butterfly board indexed by the side to move, from and to square
+    history: [[[i32; Square::Count as usize]; Square::Count as usize]; Color::Both as usize],
+}
+
+impl Default for OrderingTables {
+    fn default() -> OrderingTables {
+        OrderingTables {
+            killers: [[None; MAX_KILLERS]; MAX_PLY as usize],
+            history: [[[0; Square::Count as usize]; Square::Count as usize]; Color::Both as usize],
+        }
+    }
+}
+
+impl OrderingTables {
+    pub fn clear(&mut self) {
+        *self = OrderingTables::default();
+    }
+
+    pub fn killers(&self, ply: u32) -> [Option<Move>; MAX_KILLERS] {
+        self.killers.get(ply as usize).copied().unwrap_or_default()
+    }
+
+    pub fn history(&self, color: Color, mv: Move) -> i32 {
+        self.history[color as usize][mv.from as usize][mv.to as usize]
+    }
+
+    // A quiet move failed high, cutoffs far from the leaves count for more
+    pub fn record_cutoff(&mut self, color: Color, mv: Move, ply: u32, depth: u32) {
+        if let Some(killers) = self.killers.get_mut(ply as usize) {
+            if killers[0] != Some(mv) {
+                killers.rotate_right(1);
+                killers[0] = Some(mv);
+            }
+        }
+
+        let score = &mut self.history[color as usize][mv.from as usize][mv.to as usize];
+        *score += (depth * depth) as i32;
+        if *score >= HISTORY_LIMIT {
+            self.history
+                .iter_mut()
+                .flatten()
+                .flatten()
+                .for_each(|score| *score /= 2);
+        }
+    }
+}
+
+// Captures, en passant and promotions. Castling is encoded as the king capturing its own rook but is quiet.
+pub fn is_tactical(pos: &Position, mv: Move) -> bool {
+    is_tactical_type(MoveInfo::new(pos, mv).move_type)
+}
+
+fn is_tactical_type(move_type: MoveType) -> bool {
+    matches!(move_type, MoveType::Capture | MoveType::EnPassant | MoveType::Promotion)
+}
+
+// The victim decides, the cheaper attacker breaks ties. The king has no value so it captures first, a legal king
+// capture is never recaptured.
+fn mvv_lva(info: &MoveInfo) -> i32 {
+    let gain = PIECE_VALUES[Piece::type_of(info.captured_piece) as usize] + PIECE_VALUES[info.promotion as usize];
+    gain * 10 - PIECE_VALUES[Piece::type_of(info.moved_piece) as usize] / 10
+}
+
+pub struct MovePicker {
+    moves: MoveList,
+    scores: [i32; MAX_MOVES],
+    // moves[..tactical] are captures and promotions, the quiet moves follow
+    tactical: usize,
+    next: usize,
+    stage: Stage,
+    hash_move: Option<Move>,
+    killers: [Option<Move>; MAX_KILLERS],
+    next_killer: usize,
+}
+
+impl MovePicker {
+    // `moves` are the legal moves of `pos`. The hash move and killers are only played when they're among them, so
+    // stale ones from another position are harmless.
+    pub fn new(
+        pos: &Position,
+        moves: MoveList,
+        hash_move: Option<Move>,
+        tables: &OrderingTables,
+        ply: u32,
+    ) -> MovePicker {
+        let mut picker = MovePicker {
+            moves,
+            scores: [0; MAX_MOVES],
+            tactical: 0,
+            next: 0,
+            stage: Stage::HashMove,
+            hash_move: hash_move.filter(|&hash_move| moves.iter().any(|mv| mv == hash_move)),
+            killers: [None; MAX_KILLERS],
+            next_killer: 0,
+        };
+
+        let color = pos.side_to_move();
+        for i in 0..picker.moves.len() {
+            let mv = picker.moves.moves[i];
+            let info = MoveInfo::new(pos, mv);
+            if is_tactical_type(info.move_type) {
+                picker.scores[i] = mvv_lva(&info);
+                picker.moves.moves.swap(i, picker.tactical);
+                picker.scores.swap(i, picker.tactical);
+                picker.tactical += 1;
+            } else {
+                picker.scores[i] = tables.history(color, mv);
+            }
+        }
+
+        let quiets = &picker.moves.moves[picker.tactical..picker.moves.len()];
+        for (slot, killer) in tables.killers(ply).into_iter().enumerate() {
+            if killer != picker.hash_move && killer.is_some_and(|killer| quiets.contains(&killer)) {
+                picker.killers[slot] = killer;
+            }
+        }
+        picker
+    }
+
+    // One step of a selection sort over moves[next..end], skipping the moves an earlier stage already handed out
+    fn pick_best(&mut self, end: usize) -> Option<Move> {
+        while self.next < end {
+            let best = (self.next..end).max_by_key(|&i| self.scores[i]).unwrap_or(self.next);
+            self.moves.moves.swap(self.next, best);
+            self.scores.swap(self.next, best);
+
+            let mv = self.moves.moves[self.next];
+            self.next += 1;
+            if Some(mv) != self.hash_move && !self.killers.contains(&Some(mv)) {
+                return Some(mv);
+            }
+        }
+        None
+    }
+}
+
+impl Iterator for MovePicker {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::HashMove => {
+                    self.stage = Stage::Captures;
+                    if self.hash_move.is_some() {
+                        return self.hash_move;
+                    }
+                }
+                Stage::Captures => match self.pick_best(self.tactical) {
+                    Some(mv) => return Some(mv),
+                    None => self.stage = Stage::Killers,
+                },
+                Stage::Killers => {
+                    if self.next_killer == MAX_KILLERS {
+                        self.stage = Stage::Quiets;
+                        continue;
+                    }
+                    self.next_killer += 1;
+                    if let Some(killer) = self.killers[self.next_killer - 1] {
+                        return Some(killer);
+                    }
+                }
+                Stage::Quiets => match self.pick_best(self.moves.len()) {
+                    Some(mv) => return Some(mv),
+                    None => self.stage = Stage::Done,
+                },
+                Stage::Done => return None,
+            }
+        }
+    }
+}
@@ -0,0 +1,557 @@
+/*
+Portable Game Notation: https://www.saremba.de/chessgml/standards/pgn/pgn-complete.htm
+
+A `PgnGame` keeps the tags and the whole movetext tree, including comments, NAGs and variations, so games survive an
+import/export round trip. `PgnGame::to_game_state` replays the main line and ignores the annotations. It goes on past
+draws the players didn't claim, like any game that was played elsewhere.
+*/
+
+use crate::game::GameState;
+use crate::position::{FenError, Position};
+use crate::uci::START_FEN;
+use crate::utils::Color;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead};
+
+// export format lines have at most 79 characters
+const MAX_LINE_LENGTH: usize = 79;
+const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+// move suffix annotations and the NAG they stand for
+const SUFFIX_ANNOTATIONS: [(&str, u8); 6] = [("!", 1), ("?", 2), ("!!", 3), ("??", 4), ("!?", 5), ("?!", 6)];
+
+#[derive(Debug)]
+pub enum PgnError {
+    Io(io::Error),
+    InvalidTag(String),
+    UnterminatedComment,
+    UnbalancedVariation,
+    UnexpectedToken(String),
+    InvalidFen(FenError),
+    // ply counts from the first move of the game
+    IllegalMove { ply: usize, san: String },
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PgnError::Io(err) => write!(f, "failed to read pgn: {}", err),
+            PgnError::InvalidTag(line) => write!(f, "invalid tag pair: {}", line),
+            PgnError::UnterminatedComment => write!(f, "comment is not terminated"),
+            PgnError::UnbalancedVariation => write!(f, "variation parentheses are not balanced"),
+            PgnError::UnexpectedToken(token) => write!(f, "unexpected token: {}", token),
+            PgnError::InvalidFen(err) => write!(f, "invalid FEN tag: {}", err),
+            PgnError::IllegalMove { ply, san } => write!(f, "illegal move at ply {}: {}", ply, san),
+        }
+    }
+}
+
+impl Error for PgnError {}
+
+impl From<io::Error> for PgnError {
+    fn from(err: io::Error) -> PgnError {
+        PgnError::Io(err)
+    }
+}
+
+impl From<FenError> for PgnError {
+    fn from(err: FenError) -> PgnError {
+        PgnError::InvalidFen(err)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PgnMove {
+    pub san: String,
+    pub nags: Vec<u8>,
+    // comments in front of the move, only possible for the first move of a game or variation
+    pub leading_comments: Vec<String>,
+    pub comments: Vec<String>,
+    // alternatives to this move
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+impl PgnMove {
+    pub fn new(san: &str) -> PgnMove {
+        PgnMove {
+            san: san.into(),
+            ..PgnMove::default()
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<PgnMove>,
+    pub result: String,
+}
+
+impl PgnGame {
+    pub fn from_game(game: &GameState) -> PgnGame {
+        let result = game.outcome().score();
+        let mut pgn = PgnGame {
+            tags: Vec::new(),
+            moves: game
+                .move_history()
+                .iter()
+                .map(|mv| PgnMove::new(&mv.notation))
+                .collect(),
+            result: result.into(),
+        };
+
+        for name in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Date" => "????.??.??",
+                "Result" => result,
+                _ => "?",
+            };
+            pgn.set_tag(name, value);
+        }
+        if game.position.is_chess960() {
+            pgn.set_tag("Variant", "Chess960");
+        }
+        if game.start_fen() != START_FEN {
+            pgn.set_tag("SetUp", "1");
+            pgn.set_tag("FEN", game.start_fen());
+        }
+        pgn
+    }
+
+    // parses the first game in `text`
+    pub fn parse(text: &str) -> Result<PgnGame, PgnError> {
+        PgnReader::new(text.as_bytes())
+            .next()
+            .unwrap_or_else(|| Ok(PgnGame::default()))
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old_value)) => *old_value = value.into(),
+            None => self.tags.push((name.into(), value.into())),
+        }
+    }
+
+    pub fn start_fen(&self) -> &str {
+        self.tag("FEN").unwrap_or(START_FEN)
+    }
+
+    // drops comments, NAGs and variations, keeping only the main line
+    pub fn strip_annotations(&mut self) {
+        for mv in self.moves.iter_mut() {
+            mv.nags.clear();
+            mv.leading_comments.clear();
+            mv.comments.clear();
+            mv.variations.clear();
+        }
+    }
+
+    pub fn to_game_state(&self) -> Result<GameState, PgnError> {
+        let mut game = GameState::from_fen(self.start_fen())?;
+        if self
+            .tag("Variant")
+            .is_some_and(|variant| variant.eq_ignore_ascii_case("chess960"))
+        {
+            game.position.set_chess960(true);
+        }
+        for (ply, pgn_move) in self.moves.iter().enumerate() {
+            let illegal_move = || PgnError::IllegalMove {
+                ply,
+                san: pgn_move.san.clone(),
+            };
+            let mv = game.position.parse_san(&pgn_move.san).map_err(|_| illegal_move())?;
+            game.replay_move(mv).ok_or_else(illegal_move)?;
+        }
+        Ok(game)
+    }
+}
+
+impl fmt::Display for PgnGame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, value) in self.tags.iter() {
+            writeln!(f, "[{} \"{}\"]", name, value.replace('\\', "\\\\").replace('"', "\\\""))?;
+        }
+        writeln!(f)?;
+
+        // a FEN tag that doesn't parse is written back as is, the moves are then numbered from the first
+        let start_ply = Position::from_fen(self.start_fen()).map_or(0, |start| {
+            (start.fullmove_count().max(1) as usize - 1) * 2 + (start.side_to_move() == Color::Black) as usize
+        });
+        let mut tokens = Vec::new();
+        movetext_tokens(&self.moves, start_ply, &mut tokens);
+        tokens.push(if self.result.is_empty() { "*" } else { &self.result }.into());
+
+        let mut line_length = 0;
+        for token in tokens {
+            if line_length > 0 && line_length + token.len() + 1 > MAX_LINE_LENGTH {
+                writeln!(f)?;
+                line_length = 0;
+            }
+            if line_length > 0 {
+                write!(f, " ")?;
+                line_length += 1;
+            }
+            write!(f, "{}", token)?;
+            line_length += token.len();
+        }
+        writeln!(f)
+    }
+}
+
+// Iterates over the games of a (multi-game) PGN file
+pub struct PgnReader<R> {
+    lines: io::Lines<R>,
+    // first tag line of the next game, read while looking for the end of a game without a result
+    pending_line: Option<String>,
+    strip_annotations: bool,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> PgnReader<R> {
+        PgnReader {
+            lines: reader.lines(),
+            pending_line: None,
+            strip_annotations: false,
+        }
+    }
+
+    // skip comments, NAGs and variations while reading
+    pub fn strip_annotations(mut self) -> PgnReader<R> {
+        self.strip_annotations = true;
+        self
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<PgnGame, PgnError>;
+
+    fn next(&mut self) -> Option<Result<PgnGame, PgnError>> {
+        let mut tag_lines = Vec::new();
+        let mut movetext = String::new();
+        let mut scanner = MovetextScanner::default();
+
+        loop {
+            let line = match self.pending_line.take() {
+                Some(line) => line,
+                None => match self.lines.next() {
+                    Some(Ok(line)) => line,
+                    Some(Err(err)) => return Some(Err(err.into())),
+                    None => break,
+                },
+            };
+            let trimmed = line.trim_start_matches('\u{feff}').trim();
+
+            // lines starting with % are an escape mechanism for other software
+            if trimmed.starts_with('%') {
+                continue;
+            }
+
+            if trimmed.starts_with('[') && !scanner.in_comment {
+                if !movetext.trim().is_empty() {
+                    // the previous game ended without a result
+                    self.pending_line = Some(line);
+                    break;
+                }
+                tag_lines.push(trimmed.to_string());
+                continue;
+            }
+
+            movetext.push_str(trimmed);
+            movetext.push('\n');
+            scanner.scan_line(trimmed);
+            if scanner.terminated {
+                break;
+            }
+        }
+
+        if tag_lines.is_empty() && movetext.trim().is_empty() {
+            return None;
+        }
+
+        let game = parse_game(&tag_lines, &movetext).map(|mut game| {
+            if self.strip_annotations {
+                game.strip_annotations();
+            }
+            game
+        });
+        Some(game)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    San(String),
+    MoveNumber,
+    Nag(u8),
+    Comment(String),
+    StartVariation,
+    EndVariation,
+    Result(String),
+}
+
+fn parse_game(tag_lines: &[String], movetext: &str) -> Result<PgnGame, PgnError> {
+    let mut game = PgnGame::default();
+    for line in tag_lines {
+        let (name, value) = parse_tag(line).ok_or_else(|| PgnError::InvalidTag(line.clone()))?;
+        game.tags.push((name, value));
+    }
+
+    let mut tokens = tokenize(movetext)?.into_iter();
+    let (moves, result) = parse_moves(&mut tokens, 0)?;
+    game.moves = moves;
+    game.result = result
+        .or_else(|| game.tag("Result").map(String::from))
+        .unwrap_or("*".into());
+    Ok(game)
+}
+
+fn parse_tag(line: &str) -> Option<(String, String)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?.trim();
+    let (name, value) = inner.split_once(char::is_whitespace)?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            unescaped.push(chars.next()?);
+        } else {
+            unescaped.push(c);
+        }
+    }
+    Some((name.into(), unescaped))
+}
+
+fn tokenize(movetext: &str) -> Result<Vec<Token>, PgnError> {
+    let mut tokens = Vec::new();
+    let mut chars = movetext.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            _ if c.is_whitespace() => {
+                chars.next();
+            }
+            '{' => {
+                chars.next();
+                let mut comment = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => comment.push(c),
+                        None => return Err(PgnError::UnterminatedComment),
+                    }
+                }
+                tokens.push(Token::Comment(comment.split_whitespace().collect::<Vec<_>>().join(" ")));
+            }
+            ';' => {
+                let comment = chars.by_ref().skip(1).take_while(|&c| c != '\n').collect::<String>();
+                tokens.push(Token::Comment(comment.trim().into()));
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::StartVariation);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::EndVariation);
+            }
+            '$' => {
+                chars.next();
+                let mut digits = String::new();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                    digits.push(c);
+                    chars.next();
+                }
+                let nag = digits
+                    .parse()
+                    .map_err(|_| PgnError::UnexpectedToken(format!("${}", digits)))?;
+                tokens.push(Token::Nag(nag));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek().filter(|&&c| !c.is_whitespace() && !"{};()$".contains(c)) {
+                    word.push(c);
+                    chars.next();
+                }
+                tokenize_word(&word, &mut tokens)?;
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn tokenize_word(word: &str, tokens: &mut Vec<Token>) -> Result<(), PgnError> {
+    if RESULTS.contains(&word) {
+        tokens.push(Token::Result(word.into()));
+        return Ok(());
+    }
+
+    // move numbers ("12.", "12...") may be glued to the move that follows
+    let mut san = word;
+    let digits = san.len() - san.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if san[digits..].starts_with('.') || (digits > 0 && digits == san.len()) {
+        san = san[digits..].trim_start_matches('.');
+        tokens.push(Token::MoveNumber);
+    } else if san.starts_with('.') {
+        san = san.trim_start_matches('.');
+    }
+
+    let annotation_start = san.find(['!', '?']).unwrap_or(san.len());
+    let (san, annotation) = san.split_at(annotation_start);
+    if !san.is_empty() {
+        // castling is often written with zeros
+        if !san.starts_with(|c: char| c.is_ascii_alphabetic()) && !san.starts_with("0-0") {
+            return Err(PgnError::UnexpectedToken(word.into()));
+        }
+        tokens.push(Token::San(san.into()));
+    }
+    if !annotation.is_empty() {
+        let nag = SUFFIX_ANNOTATIONS
+            .iter()
+            .find(|(suffix, _)| *suffix == annotation)
+            .map(|&(_, nag)| nag)
+            .ok_or_else(|| PgnError::UnexpectedToken(word.into()))?;
+        tokens.push(Token::Nag(nag));
+    }
+    Ok(())
+}
+
+// Follows the movetext as it's read to find the end of a game, the game is only tokenized once it's complete
+#[derive(Default)]
+struct MovetextScanner {
+    in_comment: bool,
+    // variation nesting, a stray closing parenthesis is left for the parser to report
+    depth: usize,
+    // a game ends with its result, outside of any variation
+    terminated: bool,
+}
+
+impl MovetextScanner {
+    fn scan_line(&mut self, line: &str) {
+        let mut word_start = None;
+        // the trailing space ends the last word
+        for (i, c) in line.char_indices().chain([(line.len(), ' ')]) {
+            if self.in_comment {
+                self.in_comment = c != '}';
+                continue;
+            }
+            if !c.is_whitespace() && !"{};()$".contains(c) {
+                word_start.get_or_insert(i);
+                continue;
+            }
+            if let Some(start) = word_start.take() {
+                if self.depth == 0 && RESULTS.contains(&&line[start..i]) {
+                    self.terminated = true;
+                    return;
+                }
+            }
+            match c {
+                '{' => self.in_comment = true,
+                // the rest of the line is a comment
+                ';' => return,
+                '(' => self.depth += 1,
+                ')' => self.depth = self.depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn parse_moves(
+    tokens: &mut impl Iterator<Item = Token>,
+    depth: usize,
+) -> Result<(Vec<PgnMove>, Option<String>), PgnError> {
+    let mut moves: Vec<PgnMove> = Vec::new();
+    let mut leading_comments = Vec::new();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::San(san) => moves.push(PgnMove {
+                san,
+                leading_comments: std::mem::take(&mut leading_comments),
+                ..PgnMove::default()
+            }),
+            Token::MoveNumber => {}
+            Token::Nag(nag) => match moves.last_mut() {
+                Some(mv) => mv.nags.push(nag),
+                None => return Err(PgnError::UnexpectedToken(format!("${}", nag))),
+            },
+            Token::Comment(comment) => match moves.last_mut() {
+                Some(mv) => mv.comments.push(comment),
+                None => leading_comments.push(comment),
+            },
+            Token::StartVariation => {
+                let (variation, _) = parse_moves(tokens, depth + 1)?;
+                match moves.last_mut() {
+                    Some(mv) => mv.variations.push(variation),
+                    None => return Err(PgnError::UnexpectedToken("(".into())),
+                }
+            }
+            Token::EndVariation => {
+                if depth == 0 {
+                    return Err(PgnError::UnbalancedVariation);
+                }
+                return Ok((moves, None));
+            }
+            Token::Result(result) => {
+                if depth > 0 {
+                    return Err(PgnError::UnbalancedVariation);
+                }
+                return Ok((moves, Some(result)));
+            }
+        }
+    }
+
+    if depth > 0 {
+        return Err(PgnError::UnbalancedVariation);
+    }
+    Ok((moves, None))
+}
+
+fn movetext_tokens(moves: &[PgnMove], mut ply: usize, tokens: &mut Vec<String>) {
+    // black moves need their number repeated after anything that interrupts the move pairs
+    let mut show_number = true;
+    for mv in moves {
+        for comment in mv.leading_comments.iter() {
+            tokens.push(format_comment(comment));
+        }
+
+        if ply.is_multiple_of(2) {
+            tokens.push(format!("{}.", ply / 2 + 1));
+        } else if show_number {
+            tokens.push(format!("{}...", ply / 2 + 1));
+        }
+        tokens.push(mv.san.clone());
+        tokens.extend(mv.nags.iter().map(|nag| format!("${}", nag)));
+        tokens.extend(mv.comments.iter().map(|comment| format_comment(comment)));
+
+        for variation in mv.variations.iter() {
+            let mut variation_tokens = Vec::new();
+            movetext_tokens(variation, ply, &mut variation_tokens);
+            if let Some(first) = variation_tokens.first_mut() {
+                first.insert(0, '(');
+            }
+            if let Some(last) = variation_tokens.last_mut() {
+                last.push(')');
+            }
+            tokens.extend(variation_tokens);
+        }
+
+        show_number = !mv.comments.is_empty() || !mv.variations.is_empty();
+        ply += 1;
+    }
+}
+
+fn format_comment(comment: &str) -> String {
+    // a closing brace would end the comment early
+    format!("{{{}}}", comment.replace('}', ")"))
+}
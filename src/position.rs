@@ -1,19 +1,36 @@
-use crate::bitboards::{self, Bitboard, Bitboards};
-use crate::moves::compute;
-use crate::moves::info::{Move, MoveInfo};
+use crate::bitboards::{self, Bitboard, Bitboards, LOOKUP_TABLES};
+use crate::eval::PIECE_VALUES;
+use crate::moves::compute::{self, CASTLING_RIGHTS, KINGSIDE, QUEENSIDE};
+use crate::moves::info::{Move, MoveInfo, PROMOTION_PIECES};
 use crate::utils::{CastlingRights, Color, Direction, File, MoveType, Piece, PieceType, Rank, Square};
+use crate::zobrist::ZOBRIST;
 use num_traits::FromPrimitive;
+use std::error::Error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::time::{Duration, Instant};
 
+#[derive(Clone)]
 pub struct Position {
     pub bitboards: Bitboards,
     pub piece_masks: [Bitboard; PieceType::Pawn as usize + 1],
     pub board: [Piece; Square::Count as usize],
     pub castling_rights: CastlingRights,
+    // start squares of the castling rooks, indexed by color and `KINGSIDE`/`QUEENSIDE`
+    pub castling_rooks: [[Square; 2]; Color::Both as usize],
     pub en_passant_sq: Square,
     pub king_squares: [Square; Color::Both as usize],
     pub side_to_move: Color,
 
+    key: u64,
+
+    // Chess960 only changes how castling is written in FEN and UCI, the rules are the same in both modes
+    chess960: bool,
+
+    // The per-square legal move cache in `bitboards` only describes this position after `compute_legal_moves`.
+    // Any change to the board marks it stale and queries fall back to generating the moves on the spot.
+    legal_moves_stale: bool,
+
     total_compute_time: Duration,
     total_moves: u32,
 
@@ -28,9 +45,16 @@ impl Default for Position {
             piece_masks: [0; PieceType::Pawn as usize + 1],
             board: [Piece::Empty; Square::Count as usize],
             castling_rights: CastlingRights::default(),
+            castling_rooks: [[Square::H1, Square::A1], [Square::H8, Square::A8]],
             en_passant_sq: Square::Count,
             king_squares: [Square::Count; Color::Both as usize],
 
+            key: 0,
+
+            chess960: false,
+
+            legal_moves_stale: true,
+
             total_compute_time: Duration::default(),
             total_moves: 0,
 
@@ -41,9 +65,230 @@ impl Default for Position {
     }
 }
 
+// Positions are equal when play continues identically from them: same pieces, side to move, castling rights and
+// rooks, en passant square and clocks. The legal move cache and timing statistics don't take part.
+impl PartialEq for Position {
+    fn eq(&self, other: &Position) -> bool {
+        self.board == other.board
+            && self.side_to_move == other.side_to_move
+            && self.castling_rights == other.castling_rights
+            && self.active_castling_rooks() == other.active_castling_rooks()
+            && self.en_passant_sq == other.en_passant_sq
+            && self.halfmove_clock == other.halfmove_clock
+            && self.fullmove_count == other.fullmove_count
+    }
+}
+
+impl Eq for Position {}
+
+// the Zobrist key covers everything compared above except the clocks
+impl Hash for Position {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state);
+        self.halfmove_clock.hash(state);
+        self.fullmove_count.hash(state);
+    }
+}
+
+impl fmt::Debug for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Position(\"{}\")", self.to_fen())
+    }
+}
+
 impl Position {
+    // Panics on invalid FEN, use `from_fen` for anything that comes from the user
     pub fn new(fen: &str) -> Position {
-        init_from_fen(fen)
+        Position::from_fen(fen).unwrap_or_else(|err| panic!("invalid fen '{}': {}", fen, err))
+    }
+
+    pub fn from_fen(fen: &str) -> Result<Position, FenError> {
+        parse_fen(fen)
+    }
+
+    pub fn to_fen(&self) -> String {
+        let mut placement = Vec::new();
+        for rank in Rank::iter_reverse() {
+            let mut rank_str = String::new();
+            let mut empty_squares = 0;
+            for file in File::iter() {
+                let piece = self.board[Square::from(file, rank) as usize];
+                if piece == Piece::Empty {
+                    empty_squares += 1;
+                    continue;
+                }
+                if empty_squares > 0 {
+                    rank_str.push_str(&empty_squares.to_string());
+                    empty_squares = 0;
+                }
+                let c = PieceType::to_char(Piece::type_of(piece));
+                rank_str.push(if Piece::color_of(piece) == Color::White {
+                    c.to_ascii_uppercase()
+                } else {
+                    c
+                });
+            }
+            if empty_squares > 0 {
+                rank_str.push_str(&empty_squares.to_string());
+            }
+            placement.push(rank_str);
+        }
+
+        let side_to_move = if self.side_to_move == Color::White { "w" } else { "b" };
+
+        let castling = [Color::White, Color::Black]
+            .into_iter()
+            .flat_map(|color| [(color, KINGSIDE), (color, QUEENSIDE)])
+            .filter(|&(color, side)| {
+                self.castling_rights & CASTLING_RIGHTS[color as usize][side] != CastlingRights::NoCastling
+            })
+            .map(|(color, side)| self.castling_char(color, side))
+            .collect::<String>();
+
+        format!(
+            "{} {} {} {} {} {}",
+            placement.join("/"),
+            side_to_move,
+            if castling.is_empty() { "-".into() } else { castling },
+            self.en_passant_sq(),
+            self.halfmove_clock,
+            self.fullmove_count
+        )
+    }
+
+    // Expects the legal moves to be up to date (`compute_legal_moves`). Check, mate and annotation suffixes are
+    // accepted and ignored, castling may be written with zeros.
+    pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+        let text = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        if text.is_empty() {
+            return Err(SanError::Empty);
+        }
+        let malformed = || SanError::Malformed(san.into());
+        let illegal = || SanError::IllegalMove(san.into());
+
+        let color = self.side_to_move;
+        let castle_side = match text {
+            "O-O" | "0-0" => Some(KINGSIDE),
+            "O-O-O" | "0-0-0" => Some(QUEENSIDE),
+            _ => None,
+        };
+        if let Some(side) = castle_side {
+            let mv = Move::new(
+                self.king_squares[color as usize],
+                self.castling_rooks[color as usize][side],
+            );
+            if self.castling_side(mv) != Some(side) || !self.is_legal_move(mv.from, mv.to) {
+                return Err(illegal());
+            }
+            return Ok(mv);
+        }
+
+        let (piece_type, text) = match text.chars().next() {
+            Some(c @ ('K' | 'Q' | 'R' | 'B' | 'N')) => (PieceType::from_char(c), &text[1..]),
+            _ => (PieceType::Pawn, text),
+        };
+
+        // promotions are usually written e8=Q, but e8Q shows up as well
+        let (text, promotion) = match text.char_indices().last() {
+            Some((idx, c @ ('Q' | 'R' | 'B' | 'N'))) if piece_type == PieceType::Pawn => {
+                (text[..idx].trim_end_matches('='), PieceType::from_char(c))
+            }
+            _ if text.contains('=') => return Err(SanError::InvalidPromotion(san.into())),
+            _ => (text, PieceType::Empty),
+        };
+
+        if text.len() < 2 || !text.is_ascii() {
+            return Err(malformed());
+        }
+        let (qualifiers, to_str) = text.split_at(text.len() - 2);
+        let to = Square::parse(to_str).ok_or_else(malformed)?;
+
+        let is_capture = qualifiers.ends_with('x');
+        let mut from_file = File::Count;
+        let mut from_rank = Rank::Count;
+        for c in qualifiers.trim_end_matches('x').chars() {
+            if File::from_char(c) != File::Count && from_file == File::Count && from_rank == Rank::Count {
+                from_file = File::from_char(c);
+            } else if Rank::from_char(c) != Rank::Count && from_rank == Rank::Count {
+                from_rank = Rank::from_char(c);
+            } else {
+                return Err(malformed());
+            }
+        }
+        // a pawn only changes files when capturing, and then the file it came from is always given
+        if piece_type == PieceType::Pawn && from_file == File::Count {
+            if is_capture {
+                return Err(malformed());
+            }
+            from_file = Square::file_of(to);
+        }
+
+        let piece = Piece::from(piece_type, color);
+        let candidates = Square::iter()
+            .filter(|&sq| self.board[sq as usize] == piece)
+            .filter(|&sq| from_file == File::Count || Square::file_of(sq) == from_file)
+            .filter(|&sq| from_rank == Rank::Count || Square::rank_of(sq) == from_rank)
+            .filter(|&sq| self.is_legal_move(sq, to))
+            .collect::<Vec<_>>();
+
+        let from = match candidates[..] {
+            [] => return Err(illegal()),
+            [from] => from,
+            _ => return Err(SanError::AmbiguousMove(san.into())),
+        };
+
+        let mv = Move::with_promotion(from, to, promotion);
+        let info = MoveInfo::new(self, mv);
+        if is_capture && info.captured_piece == Piece::Empty {
+            return Err(illegal());
+        }
+        match (info.move_type == MoveType::Promotion, promotion) {
+            (true, PieceType::Empty) => Err(SanError::MissingPromotion(san.into())),
+            (true, PieceType::King | PieceType::Pawn) => Err(SanError::InvalidPromotion(san.into())),
+            (false, promotion) if promotion != PieceType::Empty => Err(SanError::InvalidPromotion(san.into())),
+            _ => Ok(mv),
+        }
+    }
+
+    // Parses coordinate notation ("e2e4", "e7e8q"), returns None unless it is a legal move. Castling is accepted as
+    // the king capturing its rook and, outside Chess960, as the king's two square step ("e1g1"). Like `parse_san` it
+    // expects the legal moves to be up to date.
+    pub fn parse_uci_move(&self, text: &str) -> Option<Move> {
+        let from = Square::parse(text.get(0..2)?)?;
+        let to = Square::parse(text.get(2..4)?)?;
+        let promotion = match text.get(4..)? {
+            "" => PieceType::Empty,
+            "q" => PieceType::Queen,
+            "r" => PieceType::Rook,
+            "b" => PieceType::Bishop,
+            "n" => PieceType::Knight,
+            _ => return None,
+        };
+
+        let mut mv = Move::with_promotion(from, to, promotion);
+        if !self.chess960 && !self.is_legal_move(from, to) {
+            mv = self.castling_move_to(from, to).unwrap_or(mv);
+        }
+        if !self.is_legal_move(mv.from, mv.to) {
+            return None;
+        }
+        let is_promotion = MoveInfo::new(self, mv).move_type == MoveType::Promotion;
+        if is_promotion != (promotion != PieceType::Empty) {
+            return None;
+        }
+        Some(mv)
+    }
+
+    // Coordinate notation of a move in this position, castling is written the way `parse_uci_move` reads it in the
+    // current mode
+    pub fn format_uci_move(&self, mv: Move) -> String {
+        match self.castling_side(mv) {
+            Some(side) if !self.chess960 => {
+                let (king_to, _) = compute::castling_destinations(self.side_to_move, side);
+                Move::new(mv.from, king_to).to_uci()
+            }
+            _ => mv.to_uci(),
+        }
     }
 
     pub fn side_to_move(&self) -> Color {
@@ -58,22 +303,182 @@ impl Position {
         self.halfmove_clock
     }
 
+    pub fn key(&self) -> u64 {
+        self.key
+    }
+
+    pub fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+
+    // Switches how castling is written in FEN and UCI, set automatically for FENs only Chess960 can produce
+    pub fn set_chess960(&mut self, chess960: bool) {
+        self.chess960 = chess960;
+    }
+
+    // `KINGSIDE` or `QUEENSIDE` when `mv` is the king capturing its own castling rook while the right is still held
+    pub fn castling_side(&self, mv: Move) -> Option<usize> {
+        let piece = self.board[mv.from as usize];
+        let color = Piece::color_of(piece);
+        if Piece::type_of(piece) != PieceType::King || self.board[mv.to as usize] != Piece::from(PieceType::Rook, color)
+        {
+            return None;
+        }
+        [KINGSIDE, QUEENSIDE].into_iter().find(|&side| {
+            self.castling_rooks[color as usize][side] == mv.to
+                && self.castling_rights & CASTLING_RIGHTS[color as usize][side] != CastlingRights::NoCastling
+        })
+    }
+
+    // The castling move that takes the king from `from` to `to`, for when castling is given by the king's destination
+    // like in standard UCI or on the board. Doesn't check legality.
+    pub fn castling_move_to(&self, from: Square, to: Square) -> Option<Move> {
+        let color = self.side_to_move;
+        [KINGSIDE, QUEENSIDE]
+            .into_iter()
+            .filter(|&side| compute::castling_destinations(color, side).0 == to)
+            .map(|side| Move::new(from, self.castling_rooks[color as usize][side]))
+            .find(|&mv| self.castling_side(mv).is_some())
+    }
+
+    // Hash of the position computed from scratch, the incrementally updated `key` must always match it
+    pub fn compute_key(&self) -> u64 {
+        let mut key = 0;
+        for sq in Square::iter() {
+            key ^= ZOBRIST.piece(self.board[sq as usize], sq);
+        }
+        key ^= ZOBRIST.castling(self.castling_rights);
+        key ^= self.en_passant_key();
+        if self.side_to_move == Color::Black {
+            key ^= ZOBRIST.side_to_move();
+        }
+        key
+    }
+
     pub fn avg_compute_time(&self) -> String {
         format!("{:?}", self.total_compute_time / self.total_moves)
     }
 
     pub fn en_passant_sq(&self) -> String {
-        if self.en_passant_sq == Square::Count {
-            "-".into()
-        } else {
-            format!("{:?}", self.en_passant_sq)
-        }
+        self.en_passant_sq.to_string()
     }
 
     pub fn king_in_check(&self, color: Color) -> bool {
-        let enemy_attacks = self.bitboards.get_attacks(!color);
-        let king_sq = self.king_squares[color as usize];
-        bitboards::is_bit_set(enemy_attacks, king_sq)
+        compute::is_square_attacked(self, self.king_squares[color as usize], !color)
+    }
+
+    // every square attacked by `color`, computed on demand
+    pub fn attacks(&self, color: Color) -> Bitboard {
+        compute::attacks_by(self, color, self.bitboards.get_checkers(Color::Both))
+    }
+
+    // Pieces of `color` attacking `sq`
+    pub fn attackers_to(&self, sq: Square, color: Color) -> Bitboard {
+        let occupancy = self.bitboards.get_checkers(Color::Both);
+        compute::attackers_to(self, sq, occupancy) & self.bitboards.get_checkers(color)
+    }
+
+    // Enemy pieces giving check to the side to move
+    pub fn checkers(&self) -> Bitboard {
+        let color = self.side_to_move;
+        self.attackers_to(self.king_squares[color as usize], !color)
+    }
+
+    // Pieces of `color` pinned to their own king
+    pub fn pinned(&self, color: Color) -> Bitboard {
+        compute::pinned_pieces(self, color)
+    }
+
+    // Whether `mv` checks the enemy king, directly or by uncovering a slider, without making it
+    pub fn gives_check(&self, mv: Move) -> bool {
+        let info = MoveInfo::new(self, mv);
+        let color = self.side_to_move;
+        let enemy_king = self.king_squares[!color as usize];
+
+        let mut occupancy = bitboards::clear_bit(self.bitboards.get_checkers(Color::Both), mv.from);
+        let mut staying = bitboards::clear_bit(self.bitboards.get_checkers(color), mv.from);
+        // the squares pieces land on, with what lands there
+        let mut landed = [(mv.to, Piece::type_of(info.moved_piece)); 2];
+        match info.move_type {
+            MoveType::Castle => {
+                let side = self.castling_side(mv).unwrap_or(KINGSIDE);
+                let (king_to, rook_to) = compute::castling_destinations(color, side);
+                occupancy = bitboards::clear_bit(occupancy, mv.to);
+                staying = bitboards::clear_bit(staying, mv.to);
+                landed = [(king_to, PieceType::King), (rook_to, PieceType::Rook)];
+            }
+            MoveType::EnPassant => occupancy = bitboards::clear_bit(occupancy, info.capture_piece_sq),
+            MoveType::Promotion => landed[0].1 = info.promotion,
+            _ => {}
+        }
+        for (sq, _) in landed {
+            occupancy = bitboards::set_bit(occupancy, sq);
+        }
+
+        // the enemy king isn't in check before the move, so any piece that stayed and now attacks it was uncovered
+        let discovered = compute::attackers_to(self, enemy_king, occupancy) & staying != 0;
+        discovered
+            || landed.into_iter().any(|(sq, piece_type)| {
+                bitboards::is_bit_set(compute::attacks_from(piece_type, color, sq, occupancy), enemy_king)
+            })
+    }
+
+    // https://www.chessprogramming.org/Static_Exchange_Evaluation
+    // Material `mv` wins once both sides have made every capture on its destination that pays off for them, least
+    // valuable attacker first. Pins are ignored and the king only recaptures on a square that is no longer defended.
+    pub fn see(&self, mv: Move) -> i32 {
+        let info = MoveInfo::new(self, mv);
+        if info.move_type == MoveType::Castle {
+            return 0;
+        }
+        let value = |piece_type: PieceType| PIECE_VALUES[piece_type as usize];
+        let promotion_gain = |piece_type: PieceType| value(piece_type) - value(PieceType::Pawn);
+
+        let mut occupancy = bitboards::clear_bit(self.bitboards.get_checkers(Color::Both), mv.from);
+        if info.move_type == MoveType::EnPassant {
+            occupancy = bitboards::clear_bit(occupancy, info.capture_piece_sq);
+        }
+
+        // gains[i] is what the side making the i-th capture is up if the exchange ends right after it
+        let mut gains = [0; Square::Count as usize];
+        gains[0] = value(Piece::type_of(info.captured_piece));
+        let mut on_square = Piece::type_of(info.moved_piece);
+        if info.move_type == MoveType::Promotion {
+            gains[0] += promotion_gain(info.promotion);
+            on_square = info.promotion;
+        }
+
+        let promotes = Square::rank_of(mv.to) == Rank::Rank1 || Square::rank_of(mv.to) == Rank::Rank8;
+        let mut side = !self.side_to_move;
+        let mut depth = 0;
+        loop {
+            let attackers = compute::attackers_to(self, mv.to, occupancy);
+            let Some((sq, attacker)) = self.least_valuable_piece(attackers & self.bitboards.get_checkers(side)) else {
+                break;
+            };
+            occupancy = bitboards::clear_bit(occupancy, sq);
+            if attacker == PieceType::King
+                && compute::attackers_to(self, mv.to, occupancy) & self.bitboards.get_checkers(!side) != 0
+            {
+                break;
+            }
+
+            depth += 1;
+            gains[depth] = value(on_square) - gains[depth - 1];
+            on_square = attacker;
+            if attacker == PieceType::Pawn && promotes {
+                gains[depth] += promotion_gain(PieceType::Queen);
+                on_square = PieceType::Queen;
+            }
+            side = !side;
+        }
+
+        // either side may stop capturing when going on would lose more
+        while depth > 0 {
+            gains[depth - 1] = -(-gains[depth - 1]).max(gains[depth]);
+            depth -= 1;
+        }
+        gains[0]
     }
 
     pub fn is_legal_move(&self, from: Square, to: Square) -> bool {
@@ -84,7 +489,7 @@ impl Position {
         if Piece::color_of(piece) != self.side_to_move {
             return false;
         }
-        self.bitboards.is_legal_move(from, to)
+        bitboards::is_bit_set(self.legal_destinations_from(from), to)
     }
 
     pub fn legal_destinations_from(&self, from: Square) -> Bitboard {
@@ -97,12 +502,30 @@ impl Position {
             return 0;
         }
 
+        if self.legal_moves_stale {
+            return compute::legal_destinations_from(self, from);
+        }
         self.bitboards.get_legal_moves(from)
     }
 
+    // Reads the cache when it's fresh, otherwise generates the moves once instead of once per square
+    pub fn has_legal_moves(&self) -> bool {
+        if self.legal_moves_stale {
+            return compute::has_legal_moves(self);
+        }
+        Square::iter().any(|sq| self.bitboards.get_legal_moves(sq) != 0)
+    }
+
+    pub fn legal_moves_stale(&self) -> bool {
+        self.legal_moves_stale
+    }
+
+    // Refreshes the legal move cache. Optional for correctness, but makes repeated legality queries on the same
+    // position cheap.
     pub fn compute_legal_moves(&mut self) {
         let start = Instant::now();
         compute::compute_legal_moves(self);
+        self.legal_moves_stale = false;
         let delta = start.elapsed();
         self.total_compute_time += delta;
         self.total_moves += 1;
@@ -110,20 +533,29 @@ impl Position {
     }
 
     pub fn move_piece(&mut self, mv: Move, validate: bool) -> Option<MoveInfo> {
-        let _start = Instant::now();
         if validate && !self.is_legal_move(mv.from, mv.to) {
             return None;
         }
 
-        let move_info = MoveInfo::new(self, mv.from, mv.to);
+        let move_info = MoveInfo::new(self, mv);
+        // a pawn reaching the last rank has to say what it becomes, like `parse_uci_move` and `parse_san` demand
+        if move_info.move_type == MoveType::Promotion && !PROMOTION_PIECES.contains(&move_info.promotion) {
+            return None;
+        }
+        self.legal_moves_stale = true;
         let moved_piece_color = Piece::color_of(move_info.moved_piece);
         let moved_piece_type = Piece::type_of(move_info.moved_piece);
 
+        // hash out the state that may change, it is hashed back in once the move is done
+        self.key ^= self.en_passant_key() ^ ZOBRIST.castling(self.castling_rights);
+
         // en passant only valid for one move
         self.en_passant_sq = Square::Count;
 
         // clear the old square first
         self.remove_piece(move_info.from);
+        // where the moved piece lands, a castling king doesn't stay on the rook it captures
+        let mut moved_to = move_info.to;
 
         // handle special moves
         match move_info.move_type {
@@ -135,33 +567,31 @@ impl Position {
                 self.remove_piece(move_info.capture_piece_sq);
             }
             MoveType::Promotion => {
-                // TODO: give user option to choose promotion piece
-                self.board[move_info.to as usize] = Piece::from(PieceType::Queen, moved_piece_color);
-                if Piece::color_of(move_info.captured_piece) != Color::Both {
+                if move_info.captured_piece != Piece::Empty {
                     self.remove_piece(move_info.capture_piece_sq);
                 }
             }
             MoveType::Castle => {
-                let (rook_from, rook_to) = match move_info.to {
-                    Square::G1 => (Square::H1, Square::F1),
-                    Square::C1 => (Square::A1, Square::D1),
-                    Square::G8 => (Square::H8, Square::F8),
-                    Square::C8 => (Square::A8, Square::D8),
-                    _ => (Square::Count, Square::Count),
-                };
-
-                let rook = self.board[rook_from as usize];
+                // the king's and rook's start and end squares can overlap, so both leave before either lands
+                let side = self.castling_rook_side(moved_piece_color, move_info.to);
+                let rook_to;
+                (moved_to, rook_to) = compute::castling_destinations(moved_piece_color, side);
+                let rook = self.board[move_info.to as usize];
+                self.remove_piece(move_info.to);
                 self.add_piece(rook_to, rook);
-                self.remove_piece(rook_from);
             }
             MoveType::Quiet | MoveType::Invalid => {}
         }
 
         // move the piece to the new square
-        self.add_piece(move_info.to, move_info.moved_piece);
+        if move_info.move_type == MoveType::Promotion {
+            self.add_piece(move_info.to, Piece::from(move_info.promotion, moved_piece_color));
+        } else {
+            self.add_piece(moved_to, move_info.moved_piece);
+        }
 
         if moved_piece_type == PieceType::King {
-            self.king_squares[moved_piece_color as usize] = move_info.to;
+            self.king_squares[moved_piece_color as usize] = moved_to;
             let rights_to_unset = match moved_piece_color {
                 Color::White => CastlingRights::WhiteCastling,
                 Color::Black => CastlingRights::BlackCastling,
@@ -170,16 +600,16 @@ impl Position {
             self.castling_rights.unset_castling_rights(rights_to_unset);
         }
 
-        if moved_piece_type == PieceType::Rook {
-            // if rook is moved from starting square, unset castling rights
-            let rights_to_unset = match (moved_piece_color, move_info.from) {
-                (Color::White, Square::A1) => CastlingRights::WhiteOOO,
-                (Color::White, Square::H1) => CastlingRights::WhiteOO,
-                (Color::Black, Square::A8) => CastlingRights::BlackOOO,
-                (Color::Black, Square::H8) => CastlingRights::BlackOO,
-                _ => CastlingRights::default(),
-            };
-            self.castling_rights.unset_castling_rights(rights_to_unset);
+        // a rook that leaves its starting square or is captured there can't castle anymore
+        for sq in [move_info.from, move_info.to] {
+            for color in [Color::White, Color::Black] {
+                for side in [KINGSIDE, QUEENSIDE] {
+                    if self.castling_rooks[color as usize][side] == sq {
+                        self.castling_rights
+                            .unset_castling_rights(CASTLING_RIGHTS[color as usize][side]);
+                    }
+                }
+            }
         }
 
         if self.side_to_move == Color::Black {
@@ -193,13 +623,13 @@ impl Position {
         }
 
         self.side_to_move = !self.side_to_move;
-
-        // println!("Time to make move: {:?}", _start.elapsed());
+        self.key ^= ZOBRIST.side_to_move() ^ ZOBRIST.castling(self.castling_rights) ^ self.en_passant_key();
 
         Some(move_info)
     }
 
     pub fn undo_move(&mut self, undo: MoveInfo) {
+        self.legal_moves_stale = true;
         let color = Piece::color_of(undo.moved_piece);
         match undo.move_type {
             MoveType::Quiet | MoveType::TwoSquarePush | MoveType::Capture | MoveType::Promotion => {
@@ -209,7 +639,7 @@ impl Position {
                 self.bitboards.unset_checkers(color, undo.to);
                 self.set_piece_mask(undo.moved_piece, undo.from);
                 let moved_to_piece = if undo.move_type == MoveType::Promotion {
-                    Piece::from(PieceType::Queen, color)
+                    Piece::from(undo.promotion, color)
                 } else {
                     undo.moved_piece
                 };
@@ -236,30 +666,14 @@ impl Position {
                 self.en_passant_sq = undo.to;
             }
             MoveType::Castle => {
-                let (rook_from, rook_to) = match undo.to {
-                    Square::G1 => (Square::H1, Square::F1),
-                    Square::C1 => (Square::A1, Square::D1),
-                    Square::G8 => (Square::H8, Square::F8),
-                    Square::C8 => (Square::A8, Square::D8),
-                    _ => (Square::Count, Square::Count),
-                };
-
-                // reset king
-                self.board[undo.from as usize] = undo.moved_piece;
-                self.board[undo.to as usize] = Piece::Empty;
-                self.bitboards.set_checkers(color, undo.from);
-                self.bitboards.unset_checkers(color, undo.to);
-                self.set_piece_mask(undo.moved_piece, undo.from);
-                self.unset_piece_mask(undo.moved_piece, undo.to);
-
-                // reset rook
+                // the key is restored below, so the hashing helpers are fine here
+                let side = self.castling_rook_side(color, undo.to);
+                let (king_to, rook_to) = compute::castling_destinations(color, side);
                 let rook = self.board[rook_to as usize];
-                self.board[rook_from as usize] = rook;
-                self.board[rook_to as usize] = Piece::Empty;
-                self.bitboards.set_checkers(color, rook_from);
-                self.bitboards.unset_checkers(color, rook_to);
-                self.set_piece_mask(rook, rook_from);
-                self.unset_piece_mask(rook, rook_to);
+                self.remove_piece(king_to);
+                self.remove_piece(rook_to);
+                self.add_piece(undo.from, undo.moved_piece);
+                self.add_piece(undo.to, rook);
             }
             MoveType::Invalid => panic!("Invalid move"),
         }
@@ -274,11 +688,94 @@ impl Position {
         self.castling_rights = undo.castling_rights;
         self.halfmove_clock = undo.halfmove_clock;
         self.fullmove_count = undo.fullmove_count;
+        self.key = undo.key;
+    }
+
+    // The en passant square is only part of the hash when a pawn can actually capture on it, otherwise positions
+    // that only differ by an unusable en passant square wouldn't count as repetitions.
+    fn en_passant_key(&self) -> u64 {
+        if self.en_passant_sq == Square::Count {
+            return 0;
+        }
+
+        let pawns = self.piece_masks[PieceType::Pawn as usize] & self.bitboards.get_checkers(self.side_to_move);
+        let capturing_pawns = LOOKUP_TABLES.get_pawn_attack_mask(!self.side_to_move, self.en_passant_sq) & pawns;
+        if capturing_pawns != 0 {
+            ZOBRIST.en_passant(self.en_passant_sq)
+        } else {
+            0
+        }
+    }
+
+    fn least_valuable_piece(&self, pieces: Bitboard) -> Option<(Square, PieceType)> {
+        [
+            PieceType::Pawn,
+            PieceType::Knight,
+            PieceType::Bishop,
+            PieceType::Rook,
+            PieceType::Queen,
+            PieceType::King,
+        ]
+        .into_iter()
+        .find_map(|piece_type| {
+            let matching = pieces & self.piece_masks[piece_type as usize];
+            let sq = Square::from_u8(matching.trailing_zeros() as u8).unwrap_or_default();
+            (matching != 0).then_some((sq, piece_type))
+        })
+    }
+
+    // X-FEN: KQkq for the outermost rook on each side, which covers every standard position, and the rook's file
+    // (Shredder-FEN) when another rook stands between it and the corner
+    fn castling_char(&self, color: Color, side: usize) -> char {
+        let rook_sq = self.castling_rooks[color as usize][side];
+        let rook_file = Square::file_of(rook_sq);
+        let rook = Piece::from(PieceType::Rook, color);
+        let has_outer_rook = Square::iter()
+            .filter(|&sq| Square::rank_of(sq) == Square::rank_of(rook_sq) && self.board[sq as usize] == rook)
+            .any(|sq| match side {
+                KINGSIDE => Square::file_of(sq) as u8 > rook_file as u8,
+                _ => (Square::file_of(sq) as u8) < rook_file as u8,
+            });
+
+        let c = match (has_outer_rook, side) {
+            (true, _) => (b'a' + rook_file as u8) as char,
+            (false, KINGSIDE) => 'k',
+            (false, _) => 'q',
+        };
+        if color == Color::White {
+            c.to_ascii_uppercase()
+        } else {
+            c
+        }
+    }
+
+    // Which castling rook of `color` starts on `rook_sq`. Only meaningful for a castling move, where the king hasn't
+    // moved yet and so both rooks are still on opposite sides of it.
+    fn castling_rook_side(&self, color: Color, rook_sq: Square) -> usize {
+        if self.castling_rooks[color as usize][KINGSIDE] == rook_sq {
+            KINGSIDE
+        } else {
+            QUEENSIDE
+        }
+    }
+
+    // castling rooks of the rights still held, the others can't matter anymore
+    fn active_castling_rooks(&self) -> [[Square; 2]; Color::Both as usize] {
+        let mut rooks = self.castling_rooks;
+        for color in [Color::White, Color::Black] {
+            for side in [KINGSIDE, QUEENSIDE] {
+                if self.castling_rights & CASTLING_RIGHTS[color as usize][side] == CastlingRights::NoCastling {
+                    rooks[color as usize][side] = Square::Count;
+                }
+            }
+        }
+        rooks
     }
 
     fn remove_piece(&mut self, sq: Square) {
         let piece = self.board[sq as usize];
         let color = Piece::color_of(piece);
+        self.key ^= ZOBRIST.piece(piece, sq);
         self.board[sq as usize] = Piece::Empty;
         self.bitboards.unset_checkers(color, sq);
         self.unset_piece_mask(piece, sq);
@@ -286,6 +783,7 @@ impl Position {
 
     fn add_piece(&mut self, sq: Square, piece: Piece) {
         let color = Piece::color_of(piece);
+        self.key ^= ZOBRIST.piece(piece, sq);
         self.board[sq as usize] = piece;
         self.bitboards.set_checkers(color, sq);
         self.set_piece_mask(piece, sq);
@@ -306,67 +804,276 @@ impl Position {
     }
 }
 
-fn init_from_fen(fen: &str) -> Position {
+#[derive(Clone, Debug, PartialEq)]
+pub enum FenError {
+    MissingField(&'static str),
+    TooManyFields,
+    RankCount(usize),
+    InvalidPiece(char),
+    InvalidDigit(char),
+    // more than eight squares described on a rank
+    RankOverflow(Rank),
+    // fewer than eight squares described on a rank
+    RankIncomplete(Rank),
+    MissingKing(Color),
+    TooManyKings(Color),
+    PawnOnBackRank(Square),
+    InvalidSideToMove(String),
+    InvalidCastling(String),
+    // the king or rook needed for this right isn't on its starting square
+    ImpossibleCastlingRights(char),
+    InvalidEnPassant(String),
+    // en passant square that no double pawn push can have produced
+    IllegalEnPassant(Square),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+    // the side that just moved left its king in check
+    OpponentInCheck,
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FenError::MissingField(field) => write!(f, "missing {} field", field),
+            FenError::TooManyFields => write!(f, "too many fields"),
+            FenError::RankCount(count) => write!(f, "expected 8 ranks, found {}", count),
+            FenError::InvalidPiece(c) => write!(f, "invalid piece '{}'", c),
+            FenError::InvalidDigit(c) => write!(f, "invalid empty square count '{}'", c),
+            FenError::RankOverflow(rank) => write!(f, "rank {} has more than 8 squares", rank),
+            FenError::RankIncomplete(rank) => write!(f, "rank {} has fewer than 8 squares", rank),
+            FenError::MissingKing(color) => write!(f, "{:?} has no king", color),
+            FenError::TooManyKings(color) => write!(f, "{:?} has more than one king", color),
+            FenError::PawnOnBackRank(sq) => write!(f, "pawn on back rank square {}", sq),
+            FenError::InvalidSideToMove(side) => write!(f, "invalid side to move '{}'", side),
+            FenError::InvalidCastling(castling) => write!(f, "invalid castling rights '{}'", castling),
+            FenError::ImpossibleCastlingRights(c) => {
+                write!(
+                    f,
+                    "castling right '{}' without king and rook on their starting squares",
+                    c
+                )
+            }
+            FenError::InvalidEnPassant(ep) => write!(f, "invalid en passant square '{}'", ep),
+            FenError::IllegalEnPassant(sq) => write!(f, "illegal en passant square {}", sq),
+            FenError::InvalidHalfmoveClock(clock) => write!(f, "invalid halfmove clock '{}'", clock),
+            FenError::InvalidFullmoveNumber(number) => write!(f, "invalid fullmove number '{}'", number),
+            FenError::OpponentInCheck => write!(f, "the side not to move is in check"),
+        }
+    }
+}
+
+impl Error for FenError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SanError {
+    Empty,
+    Malformed(String),
+    IllegalMove(String),
+    // more than one piece can make the move and the SAN doesn't say which
+    AmbiguousMove(String),
+    MissingPromotion(String),
+    InvalidPromotion(String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SanError::Empty => write!(f, "empty move"),
+            SanError::Malformed(san) => write!(f, "malformed move '{}'", san),
+            SanError::IllegalMove(san) => write!(f, "illegal move '{}'", san),
+            SanError::AmbiguousMove(san) => write!(f, "ambiguous move '{}'", san),
+            SanError::MissingPromotion(san) => write!(f, "promotion piece missing in '{}'", san),
+            SanError::InvalidPromotion(san) => write!(f, "invalid promotion in '{}'", san),
+        }
+    }
+}
+
+impl Error for SanError {}
+
+fn parse_fen(fen: &str) -> Result<Position, FenError> {
     /*
     More info about fen notation: https://www.chess.com/terms/fen-chess
+
+    The clocks are optional since they are often left out (e.g. EPD), everything else has to be present.
     */
     let mut fen_parts = fen.split_whitespace();
-
     let mut position = Position::default();
-    let mut file = File::FileA;
-    let mut rank = Rank::Rank8;
-
-    let pieces = fen_parts.next().unwrap_or("");
-    for c in pieces.chars() {
-        match c {
-            '/' => {
-                rank = rank - 1;
-                file = File::FileA;
-            }
-            c if c.is_ascii_digit() => {
-                let c_digit = c.to_digit(10).expect("Expected digit");
-                file = file + (c_digit as u8);
+
+    let pieces = fen_parts.next().ok_or(FenError::MissingField("piece placement"))?;
+    let ranks = pieces.split('/').collect::<Vec<_>>();
+    if ranks.len() != Rank::Count as usize {
+        return Err(FenError::RankCount(ranks.len()));
+    }
+    for (rank, rank_str) in Rank::iter_reverse().zip(ranks) {
+        let mut file = 0;
+        for c in rank_str.chars() {
+            if file >= File::Count as u8 {
+                return Err(FenError::RankOverflow(rank));
             }
-            _ => {
-                let color = if c.is_uppercase() { Color::White } else { Color::Black };
-                let sq = Square::from(file, rank);
-                let piece_type = PieceType::from_char(c);
-                let piece = Piece::from(piece_type, color);
-                position.add_piece(sq, piece);
-
-                if piece_type == PieceType::King {
-                    position.king_squares[color as usize] = sq;
+
+            if let Some(empty_squares) = c.to_digit(10) {
+                if !(1..=8).contains(&empty_squares) {
+                    return Err(FenError::InvalidDigit(c));
                 }
+                file += empty_squares as u8;
+                continue;
+            }
 
-                file = file + 1u8;
+            let piece_type = PieceType::from_char(c);
+            if piece_type == PieceType::Empty || !c.is_ascii() {
+                return Err(FenError::InvalidPiece(c));
+            }
+            let color = if c.is_uppercase() { Color::White } else { Color::Black };
+            let sq = Square::from(File::from_u8(file).unwrap_or_default(), rank);
+            if piece_type == PieceType::Pawn && (rank == Rank::Rank1 || rank == Rank::Rank8) {
+                return Err(FenError::PawnOnBackRank(sq));
             }
+            if piece_type == PieceType::King {
+                if position.king_squares[color as usize] != Square::Count {
+                    return Err(FenError::TooManyKings(color));
+                }
+                position.king_squares[color as usize] = sq;
+            }
+            position.add_piece(sq, Piece::from(piece_type, color));
+            file += 1;
+        }
+
+        if file > File::Count as u8 {
+            return Err(FenError::RankOverflow(rank));
+        } else if file < File::Count as u8 {
+            return Err(FenError::RankIncomplete(rank));
+        }
+    }
+    for color in [Color::White, Color::Black] {
+        if position.king_squares[color as usize] == Square::Count {
+            return Err(FenError::MissingKing(color));
         }
     }
 
-    position.side_to_move = match fen_parts.next().unwrap_or("w") {
+    position.side_to_move = match fen_parts.next().ok_or(FenError::MissingField("side to move"))? {
         "w" => Color::White,
         "b" => Color::Black,
-        _ => panic!("Invalid side to move"),
+        side => return Err(FenError::InvalidSideToMove(side.into())),
     };
 
-    let castling = fen_parts.next().unwrap_or("-");
+    let castling = fen_parts.next().ok_or(FenError::MissingField("castling rights"))?;
+    position.castling_rights = parse_castling(&mut position, castling)?;
+
+    let en_passant = fen_parts.next().ok_or(FenError::MissingField("en passant square"))?;
+    position.en_passant_sq = parse_en_passant(&position, en_passant)?;
+
+    if let Some(clock) = fen_parts.next() {
+        position.halfmove_clock = clock
+            .parse::<u32>()
+            .map_err(|_| FenError::InvalidHalfmoveClock(clock.into()))? as i32;
+    }
+    if let Some(number) = fen_parts.next() {
+        position.fullmove_count = number
+            .parse::<u32>()
+            .ok()
+            .filter(|&number| number > 0)
+            .ok_or_else(|| FenError::InvalidFullmoveNumber(number.into()))? as i32;
+    }
+    if fen_parts.next().is_some() {
+        return Err(FenError::TooManyFields);
+    }
+
+    if position.king_in_check(!position.side_to_move) {
+        return Err(FenError::OpponentInCheck);
+    }
+    position.compute_legal_moves();
+
+    position.key = position.compute_key();
+    Ok(position)
+}
+
+// Accepts KQkq, Shredder-FEN rook files (HAha) and a mix of both (X-FEN). KQkq name the outermost rook on that side
+// of the king. Anything but a king on e1/e8 castling with corner rooks turns on Chess960 mode.
+fn parse_castling(position: &mut Position, castling: &str) -> Result<CastlingRights, FenError> {
+    if castling == "-" {
+        return Ok(CastlingRights::NoCastling);
+    }
+
     let mut castling_mask = 0u8;
     for c in castling.chars() {
-        match c {
-            'K' => castling_mask |= CastlingRights::WhiteOO as u8,
-            'Q' => castling_mask |= CastlingRights::WhiteOOO as u8,
-            'k' => castling_mask |= CastlingRights::BlackOO as u8,
-            'q' => castling_mask |= CastlingRights::BlackOOO as u8,
-            _ => {}
+        let color = if c.is_ascii_uppercase() {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let back_rank = Rank::relative_rank(color, Rank::Rank1);
+        let king_sq = position.king_squares[color as usize];
+        let rook = Piece::from(PieceType::Rook, color);
+        let is_rook = |sq: &Square| position.board[*sq as usize] == rook;
+
+        let (side, rook_sq) = match c.to_ascii_lowercase() {
+            'k' => (
+                KINGSIDE,
+                File::iter()
+                    .map(|file| Square::from(file, back_rank))
+                    .skip_while(|&sq| sq != king_sq)
+                    .filter(is_rook)
+                    .last(),
+            ),
+            'q' => (
+                QUEENSIDE,
+                File::iter()
+                    .map(|file| Square::from(file, back_rank))
+                    .take_while(|&sq| sq != king_sq)
+                    .find(is_rook),
+            ),
+            'a'..='h' => {
+                let file = File::from_char(c.to_ascii_lowercase());
+                let side = if file as u8 > Square::file_of(king_sq) as u8 {
+                    KINGSIDE
+                } else {
+                    QUEENSIDE
+                };
+                (side, Some(Square::from(file, back_rank)).filter(is_rook))
+            }
+            _ => return Err(FenError::InvalidCastling(castling.into())),
+        };
+
+        let rights = CASTLING_RIGHTS[color as usize][side];
+        if castling_mask & rights as u8 != 0 {
+            return Err(FenError::InvalidCastling(castling.into()));
+        }
+        let rook_sq = match rook_sq {
+            Some(rook_sq) if Square::rank_of(king_sq) == back_rank => rook_sq,
+            _ => return Err(FenError::ImpossibleCastlingRights(c)),
+        };
+        if !matches!(c, 'K' | 'Q' | 'k' | 'q')
+            || Square::file_of(king_sq) != File::FileE
+            || ![File::FileA, File::FileH].contains(&Square::file_of(rook_sq))
+        {
+            position.chess960 = true;
         }
+        position.castling_rooks[color as usize][side] = rook_sq;
+        castling_mask |= rights as u8;
     }
-    position.castling_rights = CastlingRights::from_u8(castling_mask).unwrap_or_default();
+    Ok(CastlingRights::from_u8(castling_mask).unwrap_or_default())
+}
 
-    let en_passant_str = fen_parts.next().unwrap_or("-").to_lowercase();
-    position.en_passant_sq = Square::from_string(&en_passant_str);
+fn parse_en_passant(position: &Position, en_passant: &str) -> Result<Square, FenError> {
+    if en_passant == "-" {
+        return Ok(Square::Count);
+    }
 
-    position.halfmove_clock = fen_parts.next().unwrap_or("0").parse::<i32>().unwrap_or(0);
-    position.fullmove_count = fen_parts.next().unwrap_or("1").parse::<i32>().unwrap_or(1);
+    let sq = Square::parse(en_passant).ok_or_else(|| FenError::InvalidEnPassant(en_passant.into()))?;
 
-    position
+    // the pawn that just moved two squares stands in front of the en passant square and passed over it
+    let (ep_rank, pawn_direction) = match position.side_to_move {
+        Color::White => (Rank::Rank6, Direction::South),
+        _ => (Rank::Rank3, Direction::North),
+    };
+    let pawn_sq = sq + pawn_direction;
+    let start_sq = sq + (!pawn_direction);
+    if Square::rank_of(sq) != ep_rank
+        || position.board[sq as usize] != Piece::Empty
+        || position.board[start_sq as usize] != Piece::Empty
+        || position.board[pawn_sq as usize] != Piece::from(PieceType::Pawn, !position.side_to_move)
+    {
+        return Err(FenError::IllegalEnPassant(sq));
+    }
+    Ok(sq)
 }
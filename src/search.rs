@@ -0,0 +1,350 @@
+/*
+https://www.chessprogramming.org/Iterative_Deepening
+https://www.chessprogramming.org/Negamax
+https://www.chessprogramming.org/Alpha-Beta
+https://www.chessprogramming.org/Transposition_Table
+https://www.chessprogramming.org/Quiescence_Search
+https://www.chessprogramming.org/Move_Ordering
+*/
+
+use crate::eval::evaluate;
+use crate::moves::info::{Move, MoveList};
+use crate::moves::{generate_captures, generate_moves};
+use crate::ordering::{self, MovePicker, OrderingTables};
+use crate::position::Position;
+use crate::transposition::{Bound, SearchEntry, TranspositionTable};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub const MAX_PLY: u32 = 64;
+pub const MATE_SCORE: i32 = 32_000;
+pub const INFINITY: i32 = MATE_SCORE + 1;
+
+// how often (in nodes) the clock is consulted, reading the time on every node is measurably slow
+const TIME_CHECK_INTERVAL: u64 = 1024;
+
+#[derive(Clone, Debug)]
+pub struct SearchLimits {
+    pub depth: u32,
+    pub nodes: u64,
+    pub movetime: Option<Duration>,
+    // set from another thread to abort the search, the best move of the last finished iteration is returned
+    pub stop: Arc<AtomicBool>,
+}
+
+impl Default for SearchLimits {
+    fn default() -> SearchLimits {
+        SearchLimits {
+            depth: MAX_PLY,
+            nodes: u64::MAX,
+            movetime: None,
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub depth: u32,
+    pub nodes: u64,
+    pub pv: Vec<Move>,
+    pub elapsed: Duration,
+    // permille of the transposition table used by this search
+    pub hashfull: u32,
+}
+
+impl SearchResult {
+    // positive if the side to move mates, negative if it gets mated
+    pub fn mate_in(&self) -> Option<i32> {
+        mate_in(self.score)
+    }
+}
+
+pub fn mate_in(score: i32) -> Option<i32> {
+    if score.abs() < MATE_SCORE - MAX_PLY as i32 {
+        return None;
+    }
+    let moves = (MATE_SCORE - score.abs() + 1) / 2;
+    Some(if score > 0 { moves } else { -moves })
+}
+
+// Searches with a fresh table of the default size
+pub fn search(pos: &mut Position, limits: &SearchLimits) -> SearchResult {
+    search_with_info(pos, limits, &mut TranspositionTable::default(), |_| {})
+}
+
+// `on_iteration` is called with the result of every completed iteration, e.g. to print UCI `info` lines. The table
+// can be kept between searches of the same game.
+pub fn search_with_info(
+    pos: &mut Position,
+    limits: &SearchLimits,
+    table: &mut TranspositionTable,
+    mut on_iteration: impl FnMut(&SearchResult),
+) -> SearchResult {
+    table.new_search();
+    let mut searcher = Searcher {
+        limits,
+        table,
+        start: Instant::now(),
+        nodes: 0,
+        stopped: false,
+        root_best: None,
+        ordering: OrderingTables::default(),
+    };
+
+    let mut root_moves = MoveList::default();
+    generate_moves(pos, &mut root_moves);
+
+    // always have a move to play, even if the search is stopped before the first iteration finishes
+    let mut result = SearchResult {
+        best_move: root_moves.iter().next(),
+        ..SearchResult::default()
+    };
+    if root_moves.is_empty() {
+        result.score = if pos.king_in_check(pos.side_to_move()) {
+            -MATE_SCORE
+        } else {
+            0
+        };
+        return result;
+    }
+
+    for depth in 1..=limits.depth.min(MAX_PLY) {
+        let mut pv = Vec::new();
+        let score = searcher.negamax(pos, depth, 0, -INFINITY, INFINITY, &mut pv);
+        if searcher.stopped {
+            break;
+        }
+
+        searcher.root_best = pv.first().copied();
+        result = SearchResult {
+            best_move: searcher.root_best,
+            score,
+            depth,
+            nodes: searcher.nodes,
+            pv,
+            elapsed: searcher.start.elapsed(),
+            hashfull: searcher.table.hashfull(),
+        };
+        on_iteration(&result);
+    }
+
+    result.nodes = searcher.nodes;
+    result.elapsed = searcher.start.elapsed();
+    result
+}
+
+struct Searcher<'a> {
+    limits: &'a SearchLimits,
+    table: &'a mut TranspositionTable,
+    start: Instant,
+    nodes: u64,
+    stopped: bool,
+    // best move from the previous iteration, searched first at the root
+    root_best: Option<Move>,
+    ordering: OrderingTables,
+}
+
+impl Searcher<'_> {
+    fn negamax(
+        &mut self,
+        pos: &mut Position,
+        depth: u32,
+        ply: u32,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<Move>,
+    ) -> i32 {
+        if depth == 0 {
+            return self.quiescence(pos, ply, alpha, beta);
+        }
+
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+
+        let mut moves = MoveList::default();
+        generate_moves(pos, &mut moves);
+
+        if moves.is_empty() {
+            return if pos.king_in_check(pos.side_to_move()) {
+                // prefer the shortest mate
+                -MATE_SCORE + ply as i32
+            } else {
+                0
+            };
+        }
+
+        if ply >= MAX_PLY {
+            return evaluate(pos);
+        }
+
+        let key = pos.key();
+        let mut hash_move = None;
+        if let Some(entry) = self.table.probe(key) {
+            hash_move = entry.data.best_move;
+            // the root always searches so there is a full pv and a best move to report
+            if ply > 0 && entry.depth as u32 >= depth {
+                let score = score_from_table(entry.data.score, ply);
+                let cutoff = match entry.data.bound {
+                    Bound::Exact => true,
+                    Bound::Lower => score >= beta,
+                    Bound::Upper => score <= alpha,
+                };
+                if cutoff {
+                    if let Some(mv) = hash_move.filter(|_| entry.data.bound == Bound::Exact) {
+                        pv.push(mv);
+                    }
+                    return score.clamp(alpha, beta);
+                }
+            }
+        }
+
+        // the previous iteration's best move first at the root, the hash move everywhere else
+        let first_move = if ply == 0 {
+            self.root_best.or(hash_move)
+        } else {
+            hash_move
+        };
+
+        let original_alpha = alpha;
+        let mut best_move = None;
+        let mut child_pv = Vec::new();
+        for mv in MovePicker::new(pos, moves, first_move, &self.ordering, ply) {
+            let quiet = !ordering::is_tactical(pos, mv);
+            let undo = pos
+                .move_piece(mv, false)
+                .unwrap_or_else(|| panic!("generated invalid move: {:?} -> {:?}", mv.from, mv.to));
+            child_pv.clear();
+            let score = -self.negamax(pos, depth - 1, ply + 1, -beta, -alpha, &mut child_pv);
+            pos.undo_move(undo);
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score > alpha {
+                alpha = score;
+                best_move = Some(mv);
+                pv.clear();
+                pv.push(mv);
+                pv.extend_from_slice(&child_pv);
+            }
+            if alpha >= beta {
+                if quiet {
+                    self.ordering.record_cutoff(pos.side_to_move(), mv, ply, depth);
+                }
+                break;
+            }
+        }
+
+        let bound = if alpha >= beta {
+            Bound::Lower
+        } else if alpha > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        let entry = SearchEntry {
+            score: score_to_table(alpha, ply),
+            bound,
+            best_move: best_move.or(hash_move),
+        };
+        self.table.store(key, depth, entry);
+
+        alpha
+    }
+
+    // Resolves captures until the position is quiet, so the static evaluation is never taken in the middle of an
+    // exchange. The side to move may stand pat on the evaluation instead of capturing, except in check where every
+    // evasion is searched and having none is mate.
+    fn quiescence(&mut self, pos: &mut Position, ply: u32, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+        if ply >= MAX_PLY {
+            return evaluate(pos);
+        }
+
+        let mut moves = MoveList::default();
+        let in_check = pos.king_in_check(pos.side_to_move());
+        if in_check {
+            generate_moves(pos, &mut moves);
+            if moves.is_empty() {
+                return -MATE_SCORE + ply as i32;
+            }
+        } else {
+            let stand_pat = evaluate(pos);
+            if stand_pat >= beta {
+                return beta;
+            }
+            alpha = alpha.max(stand_pat);
+            generate_captures(pos, &mut moves);
+        }
+
+        for mv in MovePicker::new(pos, moves, None, &self.ordering, ply) {
+            // standing pat already scores at least as well as a capture that loses material
+            if !in_check && pos.see(mv) < 0 {
+                continue;
+            }
+            let undo = pos
+                .move_piece(mv, false)
+                .unwrap_or_else(|| panic!("generated invalid move: {:?} -> {:?}", mv.from, mv.to));
+            let score = -self.quiescence(pos, ply + 1, -beta, -alpha);
+            pos.undo_move(undo);
+
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+        }
+
+        alpha
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if self.stopped {
+            return true;
+        }
+
+        if self.limits.stop.load(Ordering::Relaxed) || self.nodes >= self.limits.nodes {
+            self.stopped = true;
+        } else if let Some(movetime) = self.limits.movetime {
+            if self.nodes.is_multiple_of(TIME_CHECK_INTERVAL) && self.start.elapsed() >= movetime {
+                self.stopped = true;
+            }
+        }
+
+        self.stopped
+    }
+}
+
+// Mate scores count plies from the root, the table stores them relative to the node so they stay valid when the same
+// position is reached at a different ply
+fn score_to_table(score: i32, ply: u32) -> i32 {
+    if mate_in(score).is_none() {
+        score
+    } else if score > 0 {
+        score + ply as i32
+    } else {
+        score - ply as i32
+    }
+}
+
+fn score_from_table(score: i32, ply: u32) -> i32 {
+    if mate_in(score).is_none() {
+        score
+    } else if score > 0 {
+        score - ply as i32
+    } else {
+        score + ply as i32
+    }
+}
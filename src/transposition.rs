@@ -0,0 +1,177 @@
+/*
+https://www.chessprogramming.org/Transposition_Table
+https://www.chessprogramming.org/Replacement_Strategies
+
+One entry per slot, indexed by the low bits of the Zobrist key. The full key is stored to tell positions that share a
+slot apart. An entry is replaced when it belongs to an older search (aging) or when the new one was searched at least
+as deep, so deep results survive a flood of shallow ones within the same search.
+*/
+
+use crate::moves::info::Move;
+use std::fmt;
+use std::mem;
+
+pub const DEFAULT_SIZE_MB: usize = 16;
+pub const MAX_SIZE_MB: usize = 4096;
+
+// search tables store scores, perft tables store node counts
+pub type PerftTable = TranspositionTable<u64>;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Bound {
+    #[default]
+    Exact,
+    Lower, // failed high, the real score is at least this
+    Upper, // failed low, the real score is at most this
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SearchEntry {
+    pub score: i32,
+    pub bound: Bound,
+    pub best_move: Option<Move>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Entry<T> {
+    pub key: u64,
+    pub depth: u8,
+    // 0 marks an empty slot, live entries are stamped with the search generation they were stored in
+    generation: u8,
+    pub data: T,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TableStats {
+    pub probes: u64,
+    pub hits: u64,
+    pub stores: u64,
+    // stores that evicted an entry for another position
+    pub overwrites: u64,
+}
+
+impl TableStats {
+    pub fn hit_rate(&self) -> f64 {
+        if self.probes == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / self.probes as f64
+    }
+}
+
+impl fmt::Display for TableStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "probes {}, hits {} ({:.1}%), stores {}, overwrites {}",
+            self.probes,
+            self.hits,
+            self.hit_rate() * 100.0,
+            self.stores,
+            self.overwrites
+        )
+    }
+}
+
+pub struct TranspositionTable<T = SearchEntry> {
+    entries: Vec<Entry<T>>,
+    generation: u8,
+    stats: TableStats,
+}
+
+impl<T: Copy + Default> TranspositionTable<T> {
+    // The slot count is rounded down to a power of two so the index is a mask of the key
+    pub fn new(size_mb: usize) -> TranspositionTable<T> {
+        let bytes = size_mb.clamp(1, MAX_SIZE_MB) * 1024 * 1024;
+        let slots = (bytes / mem::size_of::<Entry<T>>()).max(1);
+        TranspositionTable {
+            entries: vec![Entry::default(); 1usize << slots.ilog2()],
+            generation: 1,
+            stats: TableStats::default(),
+        }
+    }
+
+    pub fn resize(&mut self, size_mb: usize) {
+        *self = TranspositionTable::new(size_mb);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.fill(Entry::default());
+        self.generation = 1;
+        self.stats = TableStats::default();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Call before every new search, entries of earlier searches become the first to be replaced
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1).max(1);
+    }
+
+    pub fn probe(&mut self, key: u64) -> Option<Entry<T>> {
+        self.stats.probes += 1;
+        let entry = self.entries[self.index(key)];
+        if entry.generation == 0 || entry.key != key {
+            return None;
+        }
+        self.stats.hits += 1;
+        Some(entry)
+    }
+
+    pub fn store(&mut self, key: u64, depth: u32, data: T) {
+        let idx = self.index(key);
+        let generation = self.generation;
+        let slot = &mut self.entries[idx];
+
+        let depth = depth.min(u8::MAX as u32) as u8;
+        let replace = slot.generation == 0 || slot.key == key || slot.generation != generation || depth >= slot.depth;
+        if !replace {
+            return;
+        }
+
+        if slot.generation != 0 && slot.key != key {
+            self.stats.overwrites += 1;
+        }
+        self.stats.stores += 1;
+        *slot = Entry {
+            key,
+            depth,
+            generation,
+            data,
+        };
+    }
+
+    pub fn stats(&self) -> TableStats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = TableStats::default();
+    }
+
+    // Permille of slots used by the current search, sampled from the start of the table like UCI's `hashfull`
+    pub fn hashfull(&self) -> u32 {
+        let sample = &self.entries[..self.entries.len().min(1000)];
+        let used = sample
+            .iter()
+            .filter(|entry| entry.generation == self.generation)
+            .count();
+        (used * 1000 / sample.len()) as u32
+    }
+
+    fn index(&self, key: u64) -> usize {
+        key as usize & (self.entries.len() - 1)
+    }
+}
+
+impl<T: Copy + Default> Default for TranspositionTable<T> {
+    fn default() -> TranspositionTable<T> {
+        TranspositionTable::new(DEFAULT_SIZE_MB)
+    }
+}
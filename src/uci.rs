@@ -0,0 +1,285 @@
+/*
+Universal Chess Interface front-end: http://wbec-ridderkerk.nl/html/UCIProtocol.html
+
+The engine reads one command per line from `input` and writes responses to `output`. Unknown commands and malformed
+arguments are ignored as the protocol recommends.
+*/
+
+use crate::game::GameState;
+use crate::position::Position;
+use crate::search::{self, SearchLimits, SearchResult};
+use crate::transposition::{self, TranspositionTable};
+use crate::utils::Color;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, ScopedJoinHandle};
+use std::time::Duration;
+
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+const ENGINE_NAME: &str = "Oracle";
+const ENGINE_AUTHOR: &str = "jrdavison";
+
+// used to budget time when the GUI doesn't say how many moves are left until the next time control
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+// keep some time in reserve for communication overhead
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+
+struct SearchThread<'scope> {
+    handle: ScopedJoinHandle<'scope, io::Result<()>>,
+    stop: Arc<AtomicBool>,
+    infinite: bool,
+}
+
+pub fn run(input: impl BufRead, output: impl Write + Send) -> io::Result<()> {
+    let output = Mutex::new(output);
+    let mut game = GameState::new(START_FEN);
+    // kept between searches of a game, the search thread holds the lock while it runs
+    let table = Mutex::new(TranspositionTable::new(transposition::DEFAULT_SIZE_MB));
+    let mut chess960 = false;
+
+    thread::scope(|scope| {
+        let mut search_thread = None;
+
+        for line in input.lines() {
+            let line = line?;
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("uci") => {
+                    let mut output = output.lock().expect("uci output poisoned");
+                    writeln!(output, "id name {} {}", ENGINE_NAME, env!("CARGO_PKG_VERSION"))?;
+                    writeln!(output, "id author {}", ENGINE_AUTHOR)?;
+                    writeln!(
+                        output,
+                        "option name Hash type spin default {} min 1 max {}",
+                        transposition::DEFAULT_SIZE_MB,
+                        transposition::MAX_SIZE_MB
+                    )?;
+                    writeln!(output, "option name UCI_Chess960 type check default false")?;
+                    writeln!(output, "uciok")?;
+                }
+                // answered right away, even while searching
+                Some("isready") => writeln!(output.lock().expect("uci output poisoned"), "readyok")?,
+                Some("ucinewgame") => {
+                    finish_search(&mut search_thread, true)?;
+                    game = GameState::new(START_FEN);
+                    table.lock().expect("transposition table poisoned").clear();
+                }
+                Some("setoption") => {
+                    finish_search(&mut search_thread, true)?;
+                    match parse_option(tokens) {
+                        Some((name, value)) if name.eq_ignore_ascii_case("hash") => {
+                            if let Ok(size_mb) = value.parse::<usize>() {
+                                let size_mb = size_mb.clamp(1, transposition::MAX_SIZE_MB);
+                                table.lock().expect("transposition table poisoned").resize(size_mb);
+                            }
+                        }
+                        Some((name, value)) if name.eq_ignore_ascii_case("uci_chess960") => {
+                            chess960 = value.eq_ignore_ascii_case("true");
+                        }
+                        _ => {}
+                    }
+                }
+                Some("position") => {
+                    finish_search(&mut search_thread, true)?;
+                    match parse_position(tokens, chess960) {
+                        Ok(new_game) => game = new_game,
+                        Err(err) => writeln!(output.lock().expect("uci output poisoned"), "info string {}", err)?,
+                    }
+                }
+                Some("go") => {
+                    finish_search(&mut search_thread, true)?;
+                    let (limits, infinite) = parse_go(tokens, game.position.side_to_move());
+                    let stop = limits.stop.clone();
+                    let mut position = game.position.clone();
+                    let output = &output;
+                    let table = &table;
+                    let handle = scope.spawn(move || {
+                        let mut table = table.lock().expect("transposition table poisoned");
+                        run_search(&mut position, &limits, &mut table, output)
+                    });
+                    search_thread = Some(SearchThread { handle, stop, infinite });
+                }
+                Some("stop") => finish_search(&mut search_thread, true)?,
+                Some("quit") => {
+                    finish_search(&mut search_thread, true)?;
+                    break;
+                }
+                _ => {}
+            }
+            output.lock().expect("uci output poisoned").flush()?;
+        }
+
+        // input is closed, let a bounded search report its move but don't wait forever on an infinite one
+        finish_search(&mut search_thread, false)
+    })
+}
+
+fn finish_search(search_thread: &mut Option<SearchThread>, stop: bool) -> io::Result<()> {
+    if let Some(search_thread) = search_thread.take() {
+        if stop || search_thread.infinite {
+            search_thread.stop.store(true, Ordering::Relaxed);
+        }
+        search_thread.handle.join().expect("search thread panicked")?;
+    }
+    Ok(())
+}
+
+fn run_search(
+    position: &mut Position,
+    limits: &SearchLimits,
+    table: &mut TranspositionTable,
+    output: &Mutex<impl Write>,
+) -> io::Result<()> {
+    // moves are formatted against the root, castling is written differently outside Chess960
+    let root = position.clone();
+    let mut info_result = Ok(());
+    let result = search::search_with_info(position, limits, table, |info| {
+        if info_result.is_ok() {
+            info_result = write_info(&mut *output.lock().expect("uci output poisoned"), info, &root);
+        }
+    });
+    info_result?;
+
+    let mut output = output.lock().expect("uci output poisoned");
+    let best_move = result.best_move.map_or("0000".into(), |mv| root.format_uci_move(mv));
+    writeln!(output, "bestmove {}", best_move)?;
+    output.flush()
+}
+
+fn write_info(output: &mut impl Write, info: &SearchResult, root: &Position) -> io::Result<()> {
+    let score = match info.mate_in() {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", info.score),
+    };
+    let millis = info.elapsed.as_millis();
+    let nps = info.nodes as u128 * 1000 / millis.max(1);
+    let mut position = root.clone();
+    let pv = info
+        .pv
+        .iter()
+        .map(|&mv| {
+            let text = position.format_uci_move(mv);
+            position.move_piece(mv, false);
+            text
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    writeln!(
+        output,
+        "info depth {} score {} nodes {} nps {} hashfull {} time {} pv {}",
+        info.depth, score, info.nodes, nps, info.hashfull, millis, pv
+    )?;
+    output.flush()
+}
+
+fn parse_go<'a>(mut tokens: impl Iterator<Item = &'a str>, side_to_move: Color) -> (SearchLimits, bool) {
+    let mut limits = SearchLimits::default();
+    let mut bounded = false;
+    let mut time_left = [None; Color::Both as usize];
+    let mut increment = [Duration::ZERO; Color::Both as usize];
+    let mut moves_to_go = DEFAULT_MOVES_TO_GO;
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "depth" => {
+                if let Some(depth) = next_number(&mut tokens) {
+                    limits.depth = depth;
+                    bounded = true;
+                }
+            }
+            "nodes" => {
+                if let Some(nodes) = next_number(&mut tokens) {
+                    limits.nodes = nodes;
+                    bounded = true;
+                }
+            }
+            "movetime" => {
+                if let Some(millis) = next_number(&mut tokens) {
+                    limits.movetime = Some(Duration::from_millis(millis));
+                    bounded = true;
+                }
+            }
+            "wtime" => time_left[Color::White as usize] = next_number(&mut tokens).map(Duration::from_millis),
+            "btime" => time_left[Color::Black as usize] = next_number(&mut tokens).map(Duration::from_millis),
+            "winc" => {
+                increment[Color::White as usize] =
+                    next_number(&mut tokens).map_or(Duration::ZERO, Duration::from_millis)
+            }
+            "binc" => {
+                increment[Color::Black as usize] =
+                    next_number(&mut tokens).map_or(Duration::ZERO, Duration::from_millis)
+            }
+            "movestogo" => moves_to_go = next_number(&mut tokens).unwrap_or(DEFAULT_MOVES_TO_GO).max(1),
+            _ => {}
+        }
+    }
+
+    if limits.movetime.is_none() {
+        if let Some(remaining) = time_left[side_to_move as usize] {
+            let budget = remaining / moves_to_go + increment[side_to_move as usize] * 3 / 4;
+            let movetime = budget.min(remaining.saturating_sub(MOVE_OVERHEAD));
+            limits.movetime = Some(movetime.max(Duration::from_millis(1)));
+            bounded = true;
+        }
+    }
+
+    (limits, !bounded)
+}
+
+// `setoption name <id> value <x>`, returns the option's name and value
+fn parse_option<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Option<(String, &'a str)> {
+    if tokens.next() != Some("name") {
+        return None;
+    }
+    // names may contain spaces
+    let name = tokens
+        .by_ref()
+        .take_while(|&token| token != "value")
+        .collect::<Vec<_>>()
+        .join(" ");
+    Some((name, tokens.next()?))
+}
+
+fn next_number<'a, T: FromStr>(tokens: &mut impl Iterator<Item = &'a str>) -> Option<T> {
+    tokens.next()?.parse().ok()
+}
+
+// The previous position is kept when this fails, the error says why
+fn parse_position<'a>(mut tokens: impl Iterator<Item = &'a str>, chess960: bool) -> Result<GameState, String> {
+    let mut game = match tokens.next() {
+        Some("startpos") => {
+            match tokens.next() {
+                None | Some("moves") => {}
+                Some(token) => return Err(format!("expected moves after startpos, got '{}'", token)),
+            }
+            GameState::new(START_FEN)
+        }
+        Some("fen") => {
+            let fen = tokens
+                .by_ref()
+                .take_while(|&token| token != "moves")
+                .collect::<Vec<_>>()
+                .join(" ");
+            GameState::from_fen(&fen).map_err(|err| format!("invalid fen '{}': {}", fen, err))?
+        }
+        _ => return Err("expected startpos or fen".into()),
+    };
+
+    // FENs only Chess960 can produce turn it on by themselves
+    if chess960 {
+        game.position.set_chess960(true);
+    }
+
+    // "moves" is consumed above, the GUI adjudicates the game so draws and dead positions don't stop the replay
+    for token in tokens {
+        game.position
+            .parse_uci_move(token)
+            .and_then(|mv| game.replay_move(mv))
+            .ok_or_else(|| format!("illegal move {}", token))?;
+    }
+
+    Ok(game)
+}
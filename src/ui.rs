@@ -1,15 +1,35 @@
-use crate::bitboards::Bitboard;
+use crate::bitboards::{self, Bitboard};
 use crate::game::{GameMove, GameState};
-use crate::utils::{Color, File, Piece, Rank, Square};
+use crate::moves::compute::{self, KINGSIDE, QUEENSIDE};
+use crate::moves::info::{Move, MoveInfo};
+use crate::position::Position;
+use crate::search::{self, SearchLimits, SearchResult};
+use crate::transposition::TranspositionTable;
+use crate::utils::{Color, File, MoveType, Piece, PieceType, Rank, Square};
 use itertools::Itertools;
 use num_traits::FromPrimitive;
-use slint::VecModel;
+use slint::{ComponentHandle, VecModel};
 use std::cell::RefCell;
 use std::error::Error;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
 
 slint::include_modules!();
 
+const ANALYSIS_DEPTH: u32 = 6;
+// centipawn advantage at which the eval bar is roughly 3/4 filled
+const EVAL_BAR_SCALE: f32 = 400.0;
+
+// Stop flag of the background search currently feeding the eval bar, and the transposition table it shares with
+// the analyses of later positions
+#[derive(Default)]
+struct Analysis {
+    stop: RefCell<Arc<AtomicBool>>,
+    table: Arc<Mutex<TranspositionTable>>,
+}
+
 pub fn run_application() -> Result<(), Box<dyn Error>> {
     let ui = AppWindow::new()?;
 
@@ -18,30 +38,41 @@ pub fn run_application() -> Result<(), Box<dyn Error>> {
         "r1bBr3/1p3pk1/p1p1p3/n1P4n/3PP3/P5QP/B4PP1/R4RK1 b - - 0 23",
     )));
 
-    set_application_state(&ui, &game, Square::Count, true);
-    init_callbacks(&ui, &game);
+    let analysis = Rc::new(Analysis::default());
+
+    set_application_state(&ui, &game, &analysis, Square::Count, true);
+    init_callbacks(&ui, &game, &analysis);
 
-    Ok(ui.run()?)
+    let result = ui.run();
+    analysis.stop.borrow().store(true, Ordering::Relaxed);
+    Ok(result?)
 }
 
-fn set_application_state(ui: &AppWindow, game: &Rc<RefCell<GameState>>, dragged_piece: Square, refresh_position: bool) {
-    let mut game = game.borrow_mut();
+fn set_application_state(
+    ui: &AppWindow,
+    game: &Rc<RefCell<GameState>>,
+    analysis: &Analysis,
+    dragged_piece: Square,
+    refresh_position: bool,
+) {
+    let game = game.borrow();
 
     if refresh_position {
-        game.position.compute_legal_moves();
+        start_analysis(ui, &game, analysis);
         let move_history = format_move_history(&game);
         ui.set_dashboard_state(DashboardState {
             move_history: Rc::new(VecModel::from(move_history)).into(),
             halfmove_clock: game.position.halfmove_clock(),
+            result: game.outcome().to_string().into(),
             en_passant_square: game.position.en_passant_sq().into(),
             avg_compute_time: game.position.avg_compute_time().into(),
         });
     }
 
-    ui.set_board_state(build_board_state(&game, dragged_piece));
+    ui.set_board_state(build_board_state(&game, dragged_piece, None));
 }
 
-fn build_board_state(game: &GameState, dragged_piece: Square) -> BoardState {
+fn build_board_state(game: &GameState, dragged_piece: Square, pending_promotion: Option<Move>) -> BoardState {
     let pos = &game.position;
     let side_to_move = pos.side_to_move();
     let last_move = game.last_move();
@@ -49,7 +80,18 @@ fn build_board_state(game: &GameState, dragged_piece: Square) -> BoardState {
     let legal_targets = if dragged_piece == Square::Count {
         vec![false; Square::Count as usize]
     } else {
-        bitboard_to_square_flags(pos.legal_destinations_from(dragged_piece))
+        // castling shows up on the king's destination as well as on the rook it captures
+        let mut targets = pos.legal_destinations_from(dragged_piece);
+        for side in [KINGSIDE, QUEENSIDE] {
+            let (king_to, _) = compute::castling_destinations(side_to_move, side);
+            if pos
+                .castling_move_to(dragged_piece, king_to)
+                .is_some_and(|mv| bitboards::is_bit_set(targets, mv.to))
+            {
+                targets = bitboards::set_bit(targets, king_to);
+            }
+        }
+        bitboard_to_square_flags(targets)
     };
     let check_sq = if pos.king_in_check(side_to_move) {
         pos.king_squares[side_to_move as usize]
@@ -70,16 +112,80 @@ fn build_board_state(game: &GameState, dragged_piece: Square) -> BoardState {
         check_sq: check_sq as i32,
         dragged_piece_sq: dragged_piece as i32,
         dragged_piece: dragged_piece_value as i32,
+        promotion_from: pending_promotion.map_or(Square::Count, |mv| mv.from) as i32,
+        promotion_to: pending_promotion.map_or(Square::Count, |mv| mv.to) as i32,
     }
 }
 
+fn start_analysis(ui: &AppWindow, game: &GameState, analysis: &Analysis) {
+    // cancel the analysis of the previous position, its results must not reach the eval bar anymore
+    let stop = Arc::new(AtomicBool::new(false));
+    analysis.stop.replace(stop.clone()).store(true, Ordering::Relaxed);
+
+    let mut position = game.position.clone();
+    let side_to_move = position.side_to_move();
+    let ui_weak = ui.as_weak();
+    let table = analysis.table.clone();
+    let limits = SearchLimits {
+        depth: ANALYSIS_DEPTH,
+        stop: stop.clone(),
+        ..SearchLimits::default()
+    };
+
+    thread::spawn(move || {
+        let publish = |result: &SearchResult| {
+            let eval_state = build_eval_state(result, side_to_move);
+            let stop = stop.clone();
+            // the event loop is gone once the window is closed, nothing left to update then
+            let _ = ui_weak.upgrade_in_event_loop(move |ui| {
+                if !stop.load(Ordering::Relaxed) {
+                    ui.global::<RustInterface>().set_eval_state(eval_state);
+                }
+            });
+        };
+        // the cancelled analysis releases the table as soon as it sees its stop flag
+        let mut table = table.lock().expect("analysis table poisoned");
+        let result = search::search_with_info(&mut position, &limits, &mut table, &publish);
+        publish(&result);
+    });
+}
+
+fn build_eval_state(result: &SearchResult, side_to_move: Color) -> EvalState {
+    // search scores are relative to the side to move, the bar is drawn from white's point of view
+    let white_score = if side_to_move == Color::White {
+        result.score
+    } else {
+        -result.score
+    };
+
+    match search::mate_in(white_score) {
+        Some(0) => EvalState {
+            white_share: if white_score > 0 { 1.0 } else { 0.0 },
+            label: "#".into(),
+        },
+        Some(moves) => EvalState {
+            white_share: if moves > 0 { 1.0 } else { 0.0 },
+            label: format!("M{}", moves.abs()).into(),
+        },
+        None => EvalState {
+            white_share: 1.0 / (1.0 + (-(white_score as f32) / EVAL_BAR_SCALE).exp()),
+            label: format!("{:+.1}", white_score as f32 / 100.0).into(),
+        },
+    }
+}
+
+fn is_promotion(pos: &Position, mv: Move) -> bool {
+    pos.is_legal_move(mv.from, mv.to) && MoveInfo::new(pos, mv).move_type == MoveType::Promotion
+}
+
 fn bitboard_to_square_flags(mask: Bitboard) -> Vec<bool> {
     Square::iter().map(|sq| (mask & (1u64 << (sq as u8))) != 0).collect()
 }
 
-fn init_callbacks(ui: &AppWindow, game: &Rc<RefCell<GameState>>) {
+fn init_callbacks(ui: &AppWindow, game: &Rc<RefCell<GameState>>, analysis: &Rc<Analysis>) {
     let ui_weak = ui.as_weak();
     let game_weak = Rc::downgrade(game);
+    let analysis_weak = Rc::downgrade(analysis);
 
     ui.global::<RustInterface>().on_begin_drag({
         let game_weak = game_weak.clone();
@@ -94,7 +200,7 @@ fn init_callbacks(ui: &AppWindow, game: &Rc<RefCell<GameState>>) {
             } else {
                 src_sq
             };
-            ui.set_board_state(build_board_state(&game, dragged_piece));
+            ui.set_board_state(build_board_state(&game, dragged_piece, None));
         }
     });
 
@@ -108,34 +214,81 @@ fn init_callbacks(ui: &AppWindow, game: &Rc<RefCell<GameState>>) {
     ui.global::<RustInterface>().on_move_piece({
         let game_weak = game_weak.clone();
         let ui_weak = ui_weak.clone();
+        let analysis_weak = analysis_weak.clone();
         move |src: i32, dest: i32| {
             let ui: AppWindow = ui_weak.upgrade().expect("could not upgrade ui");
             let game = game_weak.upgrade().expect("could not upgrade game");
+            let analysis = analysis_weak.upgrade().expect("could not upgrade analysis");
             let mut game_mut = game.borrow_mut();
 
             let src_sq = Square::from_u8(src as u8).unwrap_or_default();
             let dest_sq = Square::from_u8(dest as u8).unwrap_or_default();
+            let mut mv = Move::new(src_sq, dest_sq);
+            if !game_mut.position.is_legal_move(src_sq, dest_sq) {
+                mv = game_mut.position.castling_move_to(src_sq, dest_sq).unwrap_or(mv);
+            }
 
-            let move_info = game_mut.play_move(src_sq, dest_sq).unwrap_or_default();
+            // let the user pick the promotion piece before playing the move
+            if is_promotion(&game_mut.position, mv) {
+                ui.set_board_state(build_board_state(&game_mut, Square::Count, Some(mv)));
+                return;
+            }
+
+            let move_info = game_mut.play_move(mv).unwrap_or_default();
+            drop(game_mut);
+
+            set_application_state(&ui, &game, &analysis, Square::Count, move_info.is_valid());
+        }
+    });
+
+    ui.global::<RustInterface>().on_promote({
+        let game_weak = game_weak.clone();
+        let ui_weak = ui_weak.clone();
+        let analysis_weak = analysis_weak.clone();
+        move |src: i32, dest: i32, piece_type: i32| {
+            let ui: AppWindow = ui_weak.upgrade().expect("could not upgrade ui");
+            let game = game_weak.upgrade().expect("could not upgrade game");
+            let analysis = analysis_weak.upgrade().expect("could not upgrade analysis");
+            let mut game_mut = game.borrow_mut();
+
+            let src_sq = Square::from_u8(src as u8).unwrap_or_default();
+            let dest_sq = Square::from_u8(dest as u8).unwrap_or_default();
+            let promotion = PieceType::from_i32(piece_type).unwrap_or_default();
+
+            let move_info = game_mut
+                .play_move(Move::with_promotion(src_sq, dest_sq, promotion))
+                .unwrap_or_default();
             drop(game_mut);
 
-            set_application_state(&ui, &game, Square::Count, move_info.is_valid());
+            set_application_state(&ui, &game, &analysis, Square::Count, move_info.is_valid());
+        }
+    });
+
+    ui.global::<RustInterface>().on_cancel_promotion({
+        let game_weak = game_weak.clone();
+        let ui_weak = ui_weak.clone();
+        move || {
+            let ui: AppWindow = ui_weak.upgrade().expect("could not upgrade ui");
+            let game = game_weak.upgrade().expect("could not upgrade game");
+            ui.set_board_state(build_board_state(&game.borrow(), Square::Count, None));
         }
     });
 
     ui.global::<RustInterface>().on_undo_move({
         let game_weak = game_weak.clone();
         let ui_weak = ui_weak.clone();
+        let analysis_weak = analysis_weak.clone();
         move || {
             let game = game_weak.upgrade().expect("could not upgrade game");
             let ui = ui_weak.upgrade().expect("could not upgrade ui");
+            let analysis = analysis_weak.upgrade().expect("could not upgrade analysis");
             let mut game_mut = game.borrow_mut();
 
             let undo_success = game_mut.undo_move();
             drop(game_mut);
 
             if undo_success {
-                set_application_state(&ui, &game, Square::Count, true);
+                set_application_state(&ui, &game, &analysis, Square::Count, true);
             }
         }
     });
@@ -143,16 +296,18 @@ fn init_callbacks(ui: &AppWindow, game: &Rc<RefCell<GameState>>) {
     ui.global::<RustInterface>().on_redo_move({
         let game_weak = game_weak.clone();
         let ui_weak = ui_weak.clone();
+        let analysis_weak = analysis_weak.clone();
         move || {
             let game = game_weak.upgrade().expect("could not upgrade game");
             let ui = ui_weak.upgrade().expect("could not upgrade ui");
+            let analysis = analysis_weak.upgrade().expect("could not upgrade analysis");
             let mut game_mut = game.borrow_mut();
 
             let redo_success = game_mut.redo_move();
             drop(game_mut);
 
             if redo_success {
-                set_application_state(&ui, &game, Square::Count, true);
+                set_application_state(&ui, &game, &analysis, Square::Count, true);
             }
         }
     });
@@ -188,7 +343,7 @@ fn format_move_history(game: &GameState) -> Vec<SlintMoveInfo> {
                 if color == Color::White {
                     len / 2
                 } else {
-                    std::cmp::max((len + 1) / 2, 1) - 1
+                    std::cmp::max(len.div_ceil(2), 1) - 1
                 }
             }
         };
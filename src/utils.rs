@@ -18,7 +18,7 @@ pub enum MoveType {
 }
 
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, Default, FromPrimitive, PartialEq, ToPrimitive)]
+#[derive(Clone, Copy, Debug, Default, Eq, FromPrimitive, Hash, PartialEq, ToPrimitive)]
 pub enum Color {
     White,
     Black,
@@ -36,7 +36,7 @@ impl Not for Color {
     }
 }
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, Default, FromPrimitive, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Eq, FromPrimitive, Hash, PartialEq)]
 pub enum PieceType {
     #[default]
     Empty,
@@ -63,6 +63,19 @@ impl PieceType {
         }
     }
 
+    // lowercase FEN letter
+    pub fn to_char(piece_type: PieceType) -> char {
+        match piece_type {
+            PieceType::King => 'k',
+            PieceType::Queen => 'q',
+            PieceType::Bishop => 'b',
+            PieceType::Knight => 'n',
+            PieceType::Rook => 'r',
+            PieceType::Pawn => 'p',
+            PieceType::Empty => ' ',
+        }
+    }
+
     pub fn make_notation_string(&self) -> &str {
         match self {
             PieceType::Pawn => "",
@@ -77,7 +90,7 @@ impl PieceType {
 }
 
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, Default, FromPrimitive, PartialEq, ToPrimitive)]
+#[derive(Clone, Copy, Debug, Default, Eq, FromPrimitive, Hash, PartialEq, ToPrimitive)]
 pub enum Piece {
     #[default]
     Empty,
@@ -115,7 +128,7 @@ impl Piece {
 
 #[repr(u8)]
 #[rustfmt::skip]
-#[derive(Clone, Copy, Debug, Default, FromPrimitive, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Default, Eq, FromPrimitive, Hash, PartialEq, PartialOrd)]
 pub enum Square {
     A1, B1, C1, D1, E1, F1, G1, H1,
     A2, B2, C2, D2, E2, F2, G2, H2,
@@ -155,6 +168,17 @@ impl Square {
         Square::from_u8((rank as u8) << 3 | (file as u8)).unwrap_or_default()
     }
 
+    // strict counterpart of `from_string`, only accepts exactly a file and a rank like "e4"
+    pub fn parse(text: &str) -> Option<Square> {
+        let mut chars = text.chars();
+        let file = File::from_char(chars.next()?);
+        let rank = Rank::from_char(chars.next()?);
+        if file == File::Count || rank == Rank::Count || chars.next().is_some() {
+            return None;
+        }
+        Some(Square::from(file, rank))
+    }
+
     pub fn from_string(square_str: &str) -> Square {
         let mut chars = square_str.chars();
         let file = File::from_char(chars.next().unwrap_or(' '));
@@ -249,7 +273,7 @@ impl File {
         }
     }
 
-    fn from_char(c: char) -> File {
+    pub fn from_char(c: char) -> File {
         match c {
             'a' => File::FileA,
             'b' => File::FileB,
@@ -333,7 +357,7 @@ impl Rank {
         }
     }
 
-    fn from_char(c: char) -> Rank {
+    pub fn from_char(c: char) -> Rank {
         match c {
             '1' => Rank::Rank1,
             '2' => Rank::Rank2,
@@ -376,6 +400,20 @@ impl Mul<u8> for Rank {
     }
 }
 
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if *self == Square::Count {
+            return write!(f, "-");
+        }
+        write!(
+            f,
+            "{}{}",
+            Square::file_of(*self).make_notation_string(),
+            Square::rank_of(*self).make_notation_string()
+        )
+    }
+}
+
 impl fmt::Display for Rank {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:?}", *self as u8 + 1)
@@ -383,7 +421,7 @@ impl fmt::Display for Rank {
 }
 
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, Default, FromPrimitive, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Eq, FromPrimitive, Hash, PartialEq)]
 pub enum CastlingRights {
     #[default]
     NoCastling = 0,
@@ -398,6 +436,14 @@ pub enum CastlingRights {
     WhiteCastling = 0b0011,
     BlackCastling = 0b1100,
     AllCastling = 0b1111,
+
+    // remaining combinations, every set of rights needs a variant or `from_u8` would drop it
+    WhiteOOOBlackOO = 0b0110,
+    WhiteOOBlackOOO = 0b1001,
+    AllButBlackOOO = 0b0111,
+    AllButBlackOO = 0b1011,
+    AllButWhiteOOO = 0b1101,
+    AllButWhiteOO = 0b1110,
 }
 
 impl BitAnd for CastlingRights {
@@ -0,0 +1,92 @@
+/*
+https://www.chessprogramming.org/Zobrist_Hashing
+
+Keys are generated at compile time from a fixed seed so hashes are stable between runs, which matters for anything
+persisted by key (opening books, saved tables).
+*/
+
+use crate::utils::{CastlingRights, File, Piece, Square};
+
+pub static ZOBRIST: ZobristKeys = ZobristKeys::init();
+
+const PIECE_COUNT: usize = Piece::BPawn as usize + 1;
+const CASTLING_COUNT: usize = CastlingRights::AllCastling as usize + 1;
+const SEED: u64 = 0x0bad_5eed_c0ff_ee42;
+
+pub struct ZobristKeys {
+    pieces: [[u64; Square::Count as usize]; PIECE_COUNT],
+    castling: [u64; CASTLING_COUNT],
+    en_passant_files: [u64; File::Count as usize],
+    side_to_move: u64,
+}
+
+impl ZobristKeys {
+    const fn init() -> ZobristKeys {
+        let mut state = SEED;
+        let mut keys = ZobristKeys {
+            pieces: [[0; Square::Count as usize]; PIECE_COUNT],
+            castling: [0; CASTLING_COUNT],
+            en_passant_files: [0; File::Count as usize],
+            side_to_move: 0,
+        };
+
+        // Piece::Empty keeps all zero keys so empty squares never change the hash
+        let mut piece = Piece::WKing as usize;
+        while piece < PIECE_COUNT {
+            let mut sq = 0;
+            while sq < Square::Count as usize {
+                keys.pieces[piece][sq] = splitmix64(&mut state);
+                sq += 1;
+            }
+            piece += 1;
+        }
+
+        // no castling rights hashes to zero
+        let mut rights = 1;
+        while rights < CASTLING_COUNT {
+            keys.castling[rights] = splitmix64(&mut state);
+            rights += 1;
+        }
+
+        let mut file = 0;
+        while file < File::Count as usize {
+            keys.en_passant_files[file] = splitmix64(&mut state);
+            file += 1;
+        }
+
+        keys.side_to_move = splitmix64(&mut state);
+        keys
+    }
+
+    pub fn piece(&self, piece: Piece, sq: Square) -> u64 {
+        if sq == Square::Count {
+            return 0;
+        }
+        self.pieces[piece as usize][sq as usize]
+    }
+
+    pub fn castling(&self, rights: CastlingRights) -> u64 {
+        self.castling[rights as usize]
+    }
+
+    pub fn en_passant(&self, sq: Square) -> u64 {
+        if sq == Square::Count {
+            return 0;
+        }
+        self.en_passant_files[Square::file_of(sq) as usize]
+    }
+
+    // xor'ed in while black is to move
+    pub fn side_to_move(&self) -> u64 {
+        self.side_to_move
+    }
+}
+
+// https://prng.di.unimi.it/splitmix64.c
+const fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
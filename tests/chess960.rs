@@ -0,0 +1,130 @@
+use oracle::chess960::{self, STANDARD_START_POSITION, START_POSITIONS};
+use oracle::game::{self, GameState};
+use oracle::moves::info::{Move, MoveInfo};
+use oracle::pgn::PgnGame;
+use oracle::position::Position;
+use oracle::uci;
+use oracle::utils::{MoveType, PieceType, Square};
+use std::collections::HashSet;
+use std::io::Cursor;
+
+// https://en.wikipedia.org/wiki/Fischer_random_chess_numbering_scheme
+#[test]
+pub fn test_start_position_numbering() {
+    assert_eq!(
+        chess960::start_fen(STANDARD_START_POSITION),
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+    );
+    assert!(chess960::start_fen(0).starts_with("bbqnnrkr/"));
+    assert!(chess960::start_fen(959).starts_with("rkrnnqbb/"));
+
+    let mut back_ranks = HashSet::new();
+    for id in 0..START_POSITIONS {
+        let rank = chess960::back_rank(id);
+        let files_of = |piece_type| (0..8).filter(move |&file| rank[file] == piece_type).collect::<Vec<_>>();
+        let bishops = files_of(PieceType::Bishop);
+        let rooks = files_of(PieceType::Rook);
+        let king = files_of(PieceType::King)[0];
+        assert_eq!(bishops.len(), 2, "{}", id);
+        assert_ne!(bishops[0] % 2, bishops[1] % 2, "{}: bishops on the same color", id);
+        assert!(rooks[0] < king && king < rooks[1], "{}: king not between the rooks", id);
+        back_ranks.insert(rank);
+        assert!(chess960::start_position(id).is_chess960());
+    }
+    assert_eq!(back_ranks.len(), START_POSITIONS as usize);
+    assert!(chess960::random_id() < START_POSITIONS);
+}
+
+#[test]
+pub fn test_castling_fields() {
+    // Shredder-FEN and X-FEN describe the same position, KQkq is written whenever it's unambiguous
+    let shredder = Position::new("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9");
+    let x_fen = Position::new("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9");
+    assert!(shredder.is_chess960() && x_fen.is_chess960());
+    assert_eq!(shredder, x_fen);
+    assert_eq!(
+        shredder.to_fen(),
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9"
+    );
+
+    // the inner of two rooks needs its file
+    let inner_rook = Position::new("4k3/8/8/8/8/8/8/RR2K3 w B - 0 1");
+    assert_eq!(inner_rook.castling_rooks[0][1], Square::B1);
+    assert_eq!(inner_rook.to_fen(), "4k3/8/8/8/8/8/8/RR2K3 w B - 0 1");
+    assert_ne!(inner_rook, Position::new("4k3/8/8/8/8/8/8/RR2K3 w Q - 0 1"));
+
+    assert!(!Position::new("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").is_chess960());
+    assert!(Position::new("r3k2r/8/8/8/8/8/8/R3K2R w HAha - 0 1").is_chess960());
+}
+
+#[test]
+pub fn test_castling_moves() {
+    // king and rooks next to each other, castling kingside doesn't move the king at all
+    let mut pos = Position::new("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9");
+    pos.compute_legal_moves();
+    let kingside = Move::new(Square::F1, Square::G1);
+    assert_eq!(pos.parse_san("O-O"), Ok(kingside));
+    assert_eq!(pos.parse_uci_move("f1g1"), Some(kingside));
+    assert_eq!(pos.format_uci_move(kingside), "f1g1");
+    assert_eq!(MoveInfo::new(&pos, kingside).move_type, MoveType::Castle);
+
+    let undo = pos.move_piece(kingside, true).expect("O-O is legal");
+    assert!(pos
+        .to_fen()
+        .starts_with("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRRKB b - -"));
+    pos.undo_move(undo);
+    assert_eq!(
+        pos,
+        Position::new("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9")
+    );
+
+    // the queenside rook has to cross the king's square
+    let mut pos = Position::new("1r2k3/8/8/8/8/8/8/1R2K3 w B - 0 1");
+    pos.compute_legal_moves();
+    let queenside = pos.parse_san("O-O-O").expect("O-O-O is legal");
+    pos.move_piece(queenside, true);
+    assert_eq!(pos.to_fen(), "1r2k3/8/8/8/8/8/8/2KR4 b - - 1 1");
+
+    // the rook shields the king's path until it moves away
+    let mut pos = Position::new("k7/8/8/8/8/8/8/2K1R2r w E - 0 1");
+    pos.compute_legal_moves();
+    assert!(!pos.is_legal_move(Square::C1, Square::E1));
+}
+
+#[test]
+pub fn test_uci_and_san_castling_notation() {
+    let mut game = GameState::new("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    let castle = game.position.parse_uci_move("e1g1").expect("e1g1 castles");
+    assert_eq!(castle, Move::new(Square::E1, Square::H1));
+    assert_eq!(game.position.parse_uci_move("e1h1"), Some(castle));
+    assert_eq!(game.position.format_uci_move(castle), "e1g1");
+
+    game.position.set_chess960(true);
+    assert_eq!(game.position.parse_uci_move("e1g1"), None);
+    assert_eq!(game.position.format_uci_move(castle), "e1h1");
+
+    let info = MoveInfo::new(&game.position, castle);
+    assert_eq!(game::algebraic_notation(&info, &game.position), "O-O");
+    game.play_move(castle).expect("castling is legal");
+
+    let pgn = PgnGame::from_game(&game);
+    assert_eq!(pgn.tag("Variant"), Some("Chess960"));
+    assert!(pgn.to_game_state().expect("valid pgn").position.is_chess960());
+}
+
+fn best_move_after(transcript: &str) -> String {
+    let mut output = Vec::new();
+    uci::run(Cursor::new(transcript), &mut output).expect("uci loop failed");
+    let output = String::from_utf8(output).expect("uci output is not utf-8");
+    output.lines().last().expect("no bestmove").into()
+}
+
+#[test]
+pub fn test_uci_option() {
+    // in Chess960 mode castling is only understood as the king capturing its rook, black can only move the king
+    let position = "position fen 4k3/8/8/8/8/8/8/4K2R w K - 0 1 moves";
+    let on = "setoption name UCI_Chess960 value true";
+    assert!(best_move_after(&format!("{}\n{} e1h1\ngo depth 1\n", on, position)).starts_with("bestmove e8"));
+    assert!(!best_move_after(&format!("{}\n{} e1g1\ngo depth 1\n", on, position)).starts_with("bestmove e8"));
+    assert!(best_move_after(&format!("{} e1g1\ngo depth 1\n", position)).starts_with("bestmove e8"));
+}
@@ -0,0 +1,16 @@
+// Helpers shared by the integration tests, each test file that needs them declares `mod common;`
+
+const PERFT_SUITE: &str = include_str!("../data/perft.epd");
+
+// EPD lines of the perft suite, without blank lines and comments
+pub fn suite_lines() -> impl Iterator<Item = &'static str> {
+    PERFT_SUITE
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+// The position of every perft suite line, its node counts stripped
+pub fn suite_fens() -> impl Iterator<Item = &'static str> {
+    suite_lines().map(|line| line.split(';').next().unwrap_or_default().trim())
+}
@@ -0,0 +1,39 @@
+use oracle::eval::{evaluate, evaluate_breakdown};
+use oracle::position::Position;
+
+fn eval_fen(fen: &str) -> i32 {
+    let mut pos = Position::new(fen);
+    pos.compute_legal_moves();
+    evaluate(&pos)
+}
+
+#[test]
+pub fn test_start_position_is_balanced() {
+    let mut pos = Position::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    pos.compute_legal_moves();
+    let breakdown = evaluate_breakdown(&pos);
+    assert_eq!(breakdown.phase, 24);
+    assert_eq!(breakdown.white_score(), 0);
+    assert_eq!(evaluate(&pos), 0);
+}
+
+#[test]
+pub fn test_mirrored_positions_are_symmetric() {
+    let white_to_move = eval_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    let black_to_move = eval_fen("r3k2r/pppbbppp/2n2q1P/1P2p3/3pn3/BN2PNP1/P1PPQPB1/R3K2R b KQkq - 0 1");
+    assert_eq!(white_to_move, black_to_move);
+}
+
+#[test]
+pub fn test_score_is_from_side_to_move() {
+    // white is a queen up
+    let white_to_move = eval_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1");
+    let black_to_move = eval_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1");
+    assert!(white_to_move > 800);
+    assert_eq!(white_to_move, -black_to_move);
+}
+
+#[test]
+pub fn test_endgame_rewards_advanced_pawns_and_central_king() {
+    assert!(eval_fen("8/8/2k5/8/8/8/4P3/4K3 w - - 0 1") < eval_fen("8/8/2k5/8/4P3/4K3/8/8 w - - 0 1"));
+}
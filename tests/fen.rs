@@ -0,0 +1,143 @@
+use num_traits::FromPrimitive;
+use oracle::moves::info::Move;
+use oracle::position::{FenError, Position};
+use oracle::utils::{CastlingRights, Color, Rank, Square};
+
+#[test]
+pub fn test_round_trip() {
+    let fens = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "r3k2r/8/8/8/8/8/8/R3K2R b Kq - 17 42",
+        "r3k2r/8/8/8/8/8/8/R3K2R w Qk - 0 1",
+        "8/8/8/8/8/8/8/K6k b - - 0 1",
+    ];
+    for fen in fens {
+        let pos = Position::from_fen(fen).unwrap_or_else(|err| panic!("{}: {}", fen, err));
+        assert_eq!(pos.to_fen(), fen);
+    }
+}
+
+#[test]
+pub fn test_every_castling_rights_combination() {
+    for bits in 0u8..16 {
+        let rights = ["K", "Q", "k", "q"]
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| bits & (1 << i) != 0)
+            .map(|(_, letter)| *letter)
+            .collect::<String>();
+        let rights = if rights.is_empty() { "-".into() } else { rights };
+        assert!(CastlingRights::from_u8(bits).is_some(), "{:04b}", bits);
+
+        let fen = format!("r3k2r/8/8/8/8/8/8/R3K2R w {} - 0 1", rights);
+        let mut pos = Position::new(&fen);
+        assert!(!pos.is_chess960());
+        assert_eq!(pos.to_fen(), fen);
+
+        // giving up white's rights keeps black's exactly as they were
+        pos.move_piece(Move::new(Square::E1, Square::E2), true)
+            .expect("king move is legal");
+        let black_rights = rights.replace(['K', 'Q'], "");
+        let black_rights = if black_rights.is_empty() || black_rights == "-" {
+            "-"
+        } else {
+            &black_rights
+        };
+        assert_eq!(
+            pos.to_fen(),
+            format!("r3k2r/8/8/8/8/8/4K3/R6R b {} - 1 1", black_rights)
+        );
+    }
+}
+
+#[test]
+pub fn test_to_fen_after_moves() {
+    let mut pos = Position::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    pos.compute_legal_moves();
+    pos.move_piece(Move::new(Square::E2, Square::E4), true).unwrap();
+    assert_eq!(
+        pos.to_fen(),
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+    );
+
+    pos.compute_legal_moves();
+    pos.move_piece(Move::new(Square::G8, Square::F6), true).unwrap();
+    assert_eq!(
+        pos.to_fen(),
+        "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 2"
+    );
+}
+
+#[test]
+pub fn test_clocks_are_optional() {
+    let pos = Position::from_fen("4k3/8/8/8/8/8/8/4K3 w - -").unwrap();
+    assert_eq!(pos.to_fen(), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+}
+
+#[test]
+pub fn test_errors() {
+    let cases = [
+        ("", FenError::MissingField("piece placement")),
+        ("4k3/8/8/8/8/8/8/4K3", FenError::MissingField("side to move")),
+        ("4k3/8/8/8/8/8/8/4K3 w", FenError::MissingField("castling rights")),
+        ("4k3/8/8/8/8/8/8/4K3 w -", FenError::MissingField("en passant square")),
+        ("4k3/8/8/8/8/8/8/4K3 w - - 0 1 extra", FenError::TooManyFields),
+        ("4k3/8/8/8/8/8/4K3 w - - 0 1", FenError::RankCount(7)),
+        ("4k3/8/8/8/8/8/8/4K2X w - - 0 1", FenError::InvalidPiece('X')),
+        ("4k3/8/8/8/8/8/8/4K2 w - - 0 1", FenError::RankIncomplete(Rank::Rank1)),
+        ("4k3/8/8/8/9/8/8/4K3 w - - 0 1", FenError::InvalidDigit('9')),
+        ("4k3/8/8/8/8/8/8/4K4 w - - 0 1", FenError::RankOverflow(Rank::Rank1)),
+        ("4k3/8/8/8/44p/8/8/4K3 w - - 0 1", FenError::RankOverflow(Rank::Rank4)),
+        ("4k3/8/8/8/8/8/8/8 w - - 0 1", FenError::MissingKing(Color::White)),
+        ("4k3/8/8/8/8/8/8/3KK3 w - - 0 1", FenError::TooManyKings(Color::White)),
+        ("4k2P/8/8/8/8/8/8/4K3 w - - 0 1", FenError::PawnOnBackRank(Square::H8)),
+        ("4k3/8/8/8/8/8/8/4K3 x - - 0 1", FenError::InvalidSideToMove("x".into())),
+        (
+            "4k3/8/8/8/8/8/8/4K2R w KK - 0 1",
+            FenError::InvalidCastling("KK".into()),
+        ),
+        ("4k3/8/8/8/8/8/8/4K2R w X - 0 1", FenError::InvalidCastling("X".into())),
+        (
+            "4k3/8/8/8/8/8/8/4K2R w Q - 0 1",
+            FenError::ImpossibleCastlingRights('Q'),
+        ),
+        (
+            "4k3/8/8/8/8/8/3K4/7R w K - 0 1",
+            FenError::ImpossibleCastlingRights('K'),
+        ),
+        (
+            "4k3/8/8/8/8/8/8/4K2R w G - 0 1",
+            FenError::ImpossibleCastlingRights('G'),
+        ),
+        (
+            "4k3/8/8/8/8/8/8/4K2R w KH - 0 1",
+            FenError::InvalidCastling("KH".into()),
+        ),
+        (
+            "4k3/8/8/8/8/8/8/4K3 w - e9 0 1",
+            FenError::InvalidEnPassant("e9".into()),
+        ),
+        ("4k3/8/8/8/8/8/8/4K3 w - e6 0 1", FenError::IllegalEnPassant(Square::E6)),
+        (
+            "4k3/8/8/4p3/8/8/8/4K3 w - e3 0 1",
+            FenError::IllegalEnPassant(Square::E3),
+        ),
+        (
+            "4k3/8/8/8/8/8/8/4K3 w - - -1 1",
+            FenError::InvalidHalfmoveClock("-1".into()),
+        ),
+        (
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 0",
+            FenError::InvalidFullmoveNumber("0".into()),
+        ),
+        ("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1", FenError::OpponentInCheck),
+    ];
+    for (fen, expected) in cases {
+        match Position::from_fen(fen) {
+            Ok(_) => panic!("accepted invalid fen '{}'", fen),
+            Err(err) => assert_eq!(err, expected, "{}", fen),
+        }
+    }
+}
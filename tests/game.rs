@@ -0,0 +1,151 @@
+use oracle::game::{GameResult, GameState};
+use oracle::moves::info::Move;
+use oracle::utils::{Color, Square};
+
+fn play(game: &mut GameState, moves: &[(Square, Square)]) {
+    for &(from, to) in moves {
+        game.play_move(Move::new(from, to))
+            .unwrap_or_else(|| panic!("illegal move: {:?} -> {:?}", from, to));
+    }
+}
+
+#[test]
+pub fn test_checkmate() {
+    let mut game = GameState::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    play(
+        &mut game,
+        &[
+            (Square::F2, Square::F3),
+            (Square::E7, Square::E5),
+            (Square::G2, Square::G4),
+        ],
+    );
+    assert_eq!(game.outcome(), GameResult::Ongoing);
+
+    play(&mut game, &[(Square::D8, Square::H4)]);
+    assert_eq!(game.outcome(), GameResult::Checkmate(Color::Black));
+    assert_eq!(game.outcome().to_string(), "0-1 (Checkmate)");
+
+    // no moves after the game ended, undoing reopens it
+    assert!(game.play_move(Move::new(Square::A2, Square::A3)).is_none());
+    assert!(game.undo_move());
+    assert_eq!(game.outcome(), GameResult::Ongoing);
+}
+
+#[test]
+pub fn test_stalemate() {
+    let mut game = GameState::new("7k/8/6Q1/8/8/8/8/K7 w - - 0 1");
+    play(&mut game, &[(Square::G6, Square::F7)]);
+    assert_eq!(game.outcome(), GameResult::Stalemate);
+    assert_eq!(game.outcome().score(), "1/2-1/2");
+}
+
+#[test]
+pub fn test_threefold_repetition() {
+    let mut game = GameState::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    let shuffle = [
+        (Square::G1, Square::F3),
+        (Square::G8, Square::F6),
+        (Square::F3, Square::G1),
+        (Square::F6, Square::G8),
+    ];
+
+    play(&mut game, &shuffle);
+    assert_eq!(game.repetitions(), 2);
+    assert_eq!(game.outcome(), GameResult::Ongoing);
+
+    play(&mut game, &shuffle);
+    assert_eq!(game.repetitions(), 3);
+    assert_eq!(game.outcome(), GameResult::ThreefoldRepetition);
+    assert!(game.outcome().is_claimable());
+    assert_eq!(game.outcome().to_string(), "Threefold repetition can be claimed");
+
+    // nobody claimed the draw, the game goes on
+    play(&mut game, &shuffle);
+    assert_eq!(game.repetitions(), 4);
+    assert_eq!(game.outcome(), GameResult::ThreefoldRepetition);
+    assert!(game.play_move(Move::new(Square::E2, Square::E4)).is_some());
+    assert_eq!(game.outcome(), GameResult::Ongoing);
+}
+
+#[test]
+pub fn test_fifty_move_rule() {
+    let mut game = GameState::new("4k3/8/8/8/8/8/4P3/R3K3 w - - 99 80");
+    assert_eq!(game.outcome(), GameResult::Ongoing);
+    play(&mut game, &[(Square::A1, Square::A2)]);
+    assert_eq!(game.outcome(), GameResult::FiftyMoveRule);
+    assert!(!game.outcome().is_over());
+    play(&mut game, &[(Square::E8, Square::F8)]);
+    assert_eq!(game.outcome(), GameResult::FiftyMoveRule);
+
+    // a pawn move resets the clock
+    let mut game = GameState::new("4k3/8/8/8/8/8/4P3/R3K3 w - - 99 80");
+    play(&mut game, &[(Square::E2, Square::E3)]);
+    assert_eq!(game.outcome(), GameResult::Ongoing);
+
+    // mate on the hundredth half move still counts
+    let mut game = GameState::new("6k1/5ppp/8/8/8/8/8/R5K1 w - - 99 80");
+    play(&mut game, &[(Square::A1, Square::A8)]);
+    assert_eq!(game.outcome(), GameResult::Checkmate(Color::White));
+}
+
+#[test]
+pub fn test_insufficient_material() {
+    let drawn = [
+        "8/8/4k3/8/8/3K4/8/8 w - - 0 1",
+        "8/8/4k3/8/8/3K4/8/6N1 w - - 0 1",
+        "8/8/4k3/8/8/3K4/8/5B2 w - - 0 1",
+        // bishops on the same square color
+        "8/3b4/4k3/8/8/3K4/8/5B2 w - - 0 1",
+    ];
+    for fen in drawn {
+        assert_eq!(
+            GameState::new(fen).outcome(),
+            GameResult::InsufficientMaterial,
+            "{}",
+            fen
+        );
+    }
+
+    let playable = [
+        "8/8/4k3/8/8/3K4/4P3/8 w - - 0 1",
+        "8/8/4k3/8/8/3K4/8/5BN1 w - - 0 1",
+        "8/8/4kn2/8/8/3K4/8/6N1 w - - 0 1",
+        // bishops on opposite square colors
+        "8/4b3/4k3/8/8/3K4/8/5B2 w - - 0 1",
+    ];
+    for fen in playable {
+        assert_eq!(GameState::new(fen).outcome(), GameResult::Ongoing, "{}", fen);
+    }
+}
+
+#[test]
+pub fn test_clone_and_equality() {
+    let mut game = GameState::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    play(&mut game, &[(Square::G1, Square::F3), (Square::G8, Square::F6)]);
+
+    let mut copy = game.clone();
+    assert_eq!(copy, game);
+    play(&mut copy, &[(Square::B1, Square::C3)]);
+    assert_ne!(copy, game);
+    assert_eq!(game.move_history().len(), 2);
+
+    // redo history doesn't count
+    assert!(copy.undo_move());
+    assert_eq!(copy, game);
+
+    // same position, different moves
+    let mut transposed = GameState::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    play(
+        &mut transposed,
+        &[
+            (Square::B1, Square::C3),
+            (Square::G8, Square::F6),
+            (Square::G1, Square::F3),
+            (Square::B8, Square::C6),
+        ],
+    );
+    play(&mut game, &[(Square::B1, Square::C3), (Square::B8, Square::C6)]);
+    assert_eq!(transposed.position, game.position);
+    assert_ne!(transposed, game);
+}
@@ -0,0 +1,150 @@
+use oracle::bitboards::{self, Bitboard};
+use oracle::moves::generate_moves;
+use oracle::moves::info::MoveList;
+use oracle::position::Position;
+use oracle::utils::{CastlingRights, Color, Piece, PieceType, Square};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+mod common;
+
+// fixed so a failure can be replayed, bump it to explore other games
+const SEED: u64 = 0x0dd_ba11_5eed;
+const GAMES_PER_FEN: usize = 2;
+const MAX_PLIES: usize = 120;
+
+// Everything make/unmake is responsible for, the caches are left out
+#[derive(Debug, PartialEq)]
+struct Snapshot {
+    board: [Piece; Square::Count as usize],
+    piece_masks: [Bitboard; PieceType::Pawn as usize + 1],
+    checkers: [Bitboard; Color::Both as usize],
+    king_squares: [Square; Color::Both as usize],
+    castling_rights: CastlingRights,
+    en_passant_sq: String,
+    side_to_move: Color,
+    halfmove_clock: i32,
+    fullmove_count: i32,
+    key: u64,
+}
+
+impl Snapshot {
+    fn of(pos: &Position) -> Snapshot {
+        Snapshot {
+            board: pos.board,
+            piece_masks: pos.piece_masks,
+            checkers: [
+                pos.bitboards.get_checkers(Color::White),
+                pos.bitboards.get_checkers(Color::Black),
+            ],
+            king_squares: pos.king_squares,
+            castling_rights: pos.castling_rights,
+            en_passant_sq: pos.en_passant_sq(),
+            side_to_move: pos.side_to_move(),
+            halfmove_clock: pos.halfmove_clock(),
+            fullmove_count: pos.fullmove_count(),
+            key: pos.key(),
+        }
+    }
+}
+
+// The board array, piece masks, color masks, king squares and hash all describe the same pieces. Returns the first
+// disagreement.
+fn check_consistent(pos: &Position) -> Result<(), String> {
+    let white = pos.bitboards.get_checkers(Color::White);
+    let black = pos.bitboards.get_checkers(Color::Black);
+    if white & black != 0 {
+        return Err(format!("squares owned by both colors: {:#x}", white & black));
+    }
+
+    for sq in Square::iter() {
+        let piece = pos.board[sq as usize];
+        let piece_type = Piece::type_of(piece);
+        for (mask_type, &mask) in pos.piece_masks.iter().enumerate().skip(PieceType::King as usize) {
+            if bitboards::is_bit_set(mask, sq) != (piece != Piece::Empty && piece_type as usize == mask_type) {
+                return Err(format!(
+                    "piece mask {} disagrees with {:?} on {:?}",
+                    mask_type, piece, sq
+                ));
+            }
+        }
+        for (color, mask) in [(Color::White, white), (Color::Black, black)] {
+            if bitboards::is_bit_set(mask, sq) != (piece != Piece::Empty && Piece::color_of(piece) == color) {
+                return Err(format!("{:?} pieces disagree with {:?} on {:?}", color, piece, sq));
+            }
+        }
+    }
+
+    for color in [Color::White, Color::Black] {
+        let king_sq = pos.king_squares[color as usize];
+        if pos.board[king_sq as usize] != Piece::from(PieceType::King, color) {
+            return Err(format!("{:?} king is not on {:?}", color, king_sq));
+        }
+    }
+    if pos.key() != pos.compute_key() {
+        return Err("incremental hash drifted".into());
+    }
+    Ok(())
+}
+
+fn starting_fens() -> Vec<&'static str> {
+    let mut fens = common::suite_fens().collect::<Vec<_>>();
+    fens.push("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    fens
+}
+
+// https://www.chessprogramming.org/Make_Move / https://www.chessprogramming.org/Unmake_Move
+// Random legal games where every legal move is made and unmade before one of them is played for real
+#[test]
+pub fn test_random_games_round_trip() {
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let mut round_trips = 0;
+
+    for fen in starting_fens() {
+        for game in 0..GAMES_PER_FEN {
+            let mut pos = Position::new(fen);
+            if let Err(problem) = check_consistent(&pos) {
+                panic!("{}: {}", fen, problem);
+            }
+
+            let mut played = Vec::new();
+            for _ in 0..MAX_PLIES {
+                let mut moves = MoveList::default();
+                generate_moves(&pos, &mut moves);
+                if moves.is_empty() {
+                    break;
+                }
+
+                let before = Snapshot::of(&pos);
+                let original = pos.clone();
+                for mv in moves.iter() {
+                    // only built on failure, it names the game so it can be replayed
+                    let context = || format!("{} game {} after {:?}, {}", fen, game, played, mv.to_uci());
+
+                    let undo = pos
+                        .move_piece(mv, false)
+                        .unwrap_or_else(|| panic!("{}: generated move rejected", context()));
+                    if let Err(problem) = check_consistent(&pos) {
+                        panic!("{}: {}", context(), problem);
+                    }
+
+                    pos.undo_move(undo);
+                    assert_eq!(
+                        Snapshot::of(&pos),
+                        before,
+                        "{}: undo didn't restore the position",
+                        context()
+                    );
+                    assert!(pos == original, "{}: undo didn't restore the position", context());
+                    round_trips += 1;
+                }
+
+                let mv = moves.moves[rng.gen_range(0..moves.len())];
+                pos.move_piece(mv, false);
+                played.push(mv.to_uci());
+            }
+        }
+    }
+
+    assert!(round_trips > 50_000, "only {} round trips", round_trips);
+}
@@ -0,0 +1,97 @@
+use oracle::moves::generate_moves;
+use oracle::moves::info::{Move, MoveList};
+use oracle::ordering::{self, MovePicker, OrderingTables};
+use oracle::position::Position;
+use oracle::utils::{Color, Square};
+use std::collections::HashSet;
+
+mod common;
+
+fn legal_moves(pos: &Position) -> MoveList {
+    let mut moves = MoveList::default();
+    generate_moves(pos, &mut moves);
+    moves
+}
+
+#[test]
+pub fn test_picker_yields_every_move_once() {
+    let mut tables = OrderingTables::default();
+    for fen in common::suite_fens() {
+        let pos = Position::new(fen);
+        let moves = legal_moves(&pos);
+        let Some(last) = moves.iter().last() else {
+            continue;
+        };
+        tables.record_cutoff(pos.side_to_move(), moves.moves[0], 0, 3);
+
+        // the hash move comes first, a hash move that isn't legal here is ignored
+        for hash_move in [Some(last), Some(Move::new(Square::A1, Square::A1)), None] {
+            let picked = MovePicker::new(&pos, moves, hash_move, &tables, 0).collect::<Vec<_>>();
+            assert_eq!(picked.len(), moves.len(), "{}", fen);
+            assert_eq!(
+                picked.iter().copied().collect::<HashSet<_>>(),
+                moves.iter().collect::<HashSet<_>>(),
+            );
+            if hash_move == Some(last) {
+                assert_eq!(picked[0], last, "{}", fen);
+            }
+        }
+    }
+}
+
+#[test]
+pub fn test_picker_stages() {
+    // the queen on d5 is attacked by the pawn and the rook, the pawn on a7 by the rook
+    let pos = Position::new("4k3/r7/8/3q4/4P3/8/8/R2RK3 w - - 0 1");
+    let moves = legal_moves(&pos);
+    let mut tables = OrderingTables::default();
+
+    let killer = Move::new(Square::E1, Square::F2);
+    let other_killer = Move::new(Square::E1, Square::E2);
+    tables.record_cutoff(Color::White, killer, 1, 2);
+    tables.record_cutoff(Color::White, other_killer, 1, 2);
+    // a killer from the other ply doesn't count
+    tables.record_cutoff(Color::White, Move::new(Square::E1, Square::F1), 2, 2);
+    // history orders the remaining quiet moves
+    let good_quiet = Move::new(Square::D1, Square::D4);
+    tables.record_cutoff(Color::White, good_quiet, 3, 6);
+
+    let hash_move = Move::new(Square::A1, Square::A2);
+    let picked = MovePicker::new(&pos, moves, Some(hash_move), &tables, 1).collect::<Vec<_>>();
+    assert_eq!(
+        picked[..6],
+        [
+            hash_move,
+            // the cheapest attacker of the most valuable victim first
+            Move::new(Square::E4, Square::D5),
+            Move::new(Square::D1, Square::D5),
+            Move::new(Square::A1, Square::A7),
+            // the latest killer first
+            other_killer,
+            killer,
+        ]
+    );
+    assert_eq!(picked[6], good_quiet);
+    assert!(ordering::is_tactical(&pos, Move::new(Square::A1, Square::A7)));
+    assert!(!ordering::is_tactical(&pos, good_quiet));
+}
+
+#[test]
+pub fn test_tables() {
+    let mut tables = OrderingTables::default();
+    let first = Move::new(Square::G1, Square::F3);
+    let second = Move::new(Square::B1, Square::C3);
+    tables.record_cutoff(Color::White, first, 4, 3);
+    tables.record_cutoff(Color::White, first, 4, 2);
+    assert_eq!(tables.killers(4), [Some(first), None]);
+    assert_eq!(tables.history(Color::White, first), 13);
+    assert_eq!(tables.history(Color::Black, first), 0);
+
+    tables.record_cutoff(Color::White, second, 4, 1);
+    assert_eq!(tables.killers(4), [Some(second), Some(first)]);
+    assert_eq!(tables.killers(5), [None, None]);
+
+    tables.clear();
+    assert_eq!(tables.killers(4), [None, None]);
+    assert_eq!(tables.history(Color::White, first), 0);
+}
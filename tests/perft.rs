@@ -1,7 +1,19 @@
-use oracle::moves::count_legal_moves;
+use oracle::moves::info::{MoveInfo, MoveList};
+use oracle::moves::{
+    count_legal_moves, count_legal_moves_parallel, generate_captures, generate_moves, perft_divide,
+    perft_divide_parallel,
+};
 use oracle::position::Position;
+use oracle::utils::MoveType;
+use std::process::Command;
+use std::thread;
 use std::time::Instant;
 
+mod common;
+
+// depths with more nodes than this are only counted by the ignored deep run
+const FAST_NODE_LIMIT: u64 = 100_000;
+
 // https://www.chessprogramming.org/Perft
 #[test]
 pub fn test_basic_count_up_to_5ply() {
@@ -59,18 +71,158 @@ pub fn test_basic_count_up_to_5ply() {
     // assert_eq!(legal_10ply, 69352859712417);
 }
 
-// #[test]
-// pub fn test_kiwipete() {
-//     let mut pos = Position::new("r3k2r/p1ppqpb1/bn2pnp1/2pP4/1p2P3/2N2N2/PPQ1BPPP/R1B1K2R w KQkq - 0 1");
-//     assert_eq!(count_legal_moves(&mut pos, 1), 48);
-//     assert_eq!(count_legal_moves(&mut pos, 2), 2039);
-//     assert_eq!(count_legal_moves(&mut pos, 3), 97862);
-// }
-
-// #[test]
-// pub fn test_position_3() {
-//     let mut pos = Position::new("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8");
-//     assert_eq!(count_legal_moves(&mut pos, 1), 44);
-//     assert_eq!(count_legal_moves(&mut pos, 2), 1486);
-//     assert_eq!(count_legal_moves(&mut pos, 3), 62379);
-// }
+#[test]
+pub fn test_promotions() {
+    // every promotion square reachable with captures and under-promotions for both sides
+    let mut pos = Position::new("n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1");
+    assert_eq!(count_legal_moves(&mut pos, 1), 24);
+    assert_eq!(count_legal_moves(&mut pos, 2), 496);
+    assert_eq!(count_legal_moves(&mut pos, 3), 9483);
+    assert_eq!(count_legal_moves(&mut pos, 4), 182838);
+}
+
+#[test]
+pub fn test_divide() {
+    let mut pos = Position::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    let divide = perft_divide(&mut pos, 3);
+    assert_eq!(divide.len(), 20);
+    assert_eq!(divide.iter().map(|&(_, nodes)| nodes).sum::<u64>(), 8902);
+
+    let e2e4 = divide
+        .iter()
+        .find(|(mv, _)| mv.to_uci() == "e2e4")
+        .expect("e2e4 missing");
+    assert_eq!(e2e4.1, 600);
+}
+
+#[test]
+pub fn test_parallel_matches_serial() {
+    for fen in [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    ] {
+        let mut pos = Position::new(fen);
+        let serial = perft_divide(&mut pos, 3);
+        for threads in [1, 3, 64] {
+            assert_eq!(
+                perft_divide_parallel(&pos, 3, threads),
+                serial,
+                "{} on {} threads",
+                fen,
+                threads
+            );
+        }
+        let total = serial.iter().map(|&(_, nodes)| nodes).sum::<u64>();
+        assert_eq!(count_legal_moves_parallel(&pos, 3, 4), total);
+        assert_eq!(count_legal_moves_parallel(&pos, 1, 4), count_legal_moves(&mut pos, 1));
+        assert_eq!(pos.to_fen(), fen);
+    }
+}
+
+// cargo test --release --test perft -- --ignored
+#[test]
+#[ignore]
+pub fn test_parallel_start_position_7ply() {
+    let pos = Position::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+    assert_eq!(count_legal_moves_parallel(&pos, 7, threads), 3195901860);
+}
+
+#[test]
+pub fn test_perft_subcommand() {
+    let output = Command::new(env!("CARGO_BIN_EXE_oracle"))
+        .args(["perft", "2", "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"])
+        .output()
+        .expect("failed to run oracle");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert!(lines.contains(&"e2e4: 5"));
+    assert!(lines.contains(&"Nodes searched: 30"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_oracle"))
+        .args(["perft", "3", "--threads", "2"])
+        .output()
+        .expect("failed to run oracle");
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("Nodes searched: 8902"));
+}
+
+#[test]
+pub fn test_perft_suite() {
+    run_perft_suite(FAST_NODE_LIMIT);
+}
+
+// cargo test --release --test perft -- --ignored
+#[test]
+#[ignore]
+pub fn test_perft_suite_deep() {
+    run_perft_suite(u64::MAX);
+}
+
+#[test]
+pub fn test_captures_match_filtered_moves() {
+    for fen in common::suite_fens() {
+        check_captures(&mut Position::new(fen), 2);
+    }
+}
+
+// Every position within `depth` plies: the capture generator yields exactly the captures, en passants and
+// promotions among the legal moves, in the same order
+fn check_captures(pos: &mut Position, depth: u32) {
+    let mut moves = MoveList::default();
+    let mut captures = MoveList::default();
+    generate_moves(pos, &mut moves);
+    generate_captures(pos, &mut captures);
+
+    let expected = moves
+        .iter()
+        .filter(|&mv| {
+            let move_type = MoveInfo::new(pos, mv).move_type;
+            matches!(move_type, MoveType::Capture | MoveType::EnPassant | MoveType::Promotion)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(captures.iter().collect::<Vec<_>>(), expected, "{}", pos.to_fen());
+
+    if depth > 1 {
+        for mv in moves.iter() {
+            let undo = pos.move_piece(mv, false).expect("generated move is legal");
+            check_captures(pos, depth - 1);
+            pos.undo_move(undo);
+        }
+    }
+}
+
+fn run_perft_suite(node_limit: u64) {
+    let mut mismatches = Vec::new();
+    for line in common::suite_lines() {
+        let mut fields = line.split(';');
+        let fen = fields.next().unwrap_or_default().trim();
+        let mut pos = Position::new(fen);
+        for field in fields {
+            let (depth, expected) = field
+                .trim()
+                .strip_prefix('D')
+                .and_then(|counts| counts.split_once(' '))
+                .unwrap_or_else(|| panic!("malformed perft entry '{}' for {}", field, fen));
+            let depth = depth.parse::<u32>().expect("invalid depth");
+            let expected = expected.parse::<u64>().expect("invalid node count");
+            if expected > node_limit {
+                continue;
+            }
+
+            let nodes = count_legal_moves(&mut pos, depth);
+            if nodes != expected {
+                mismatches.push(format!(
+                    "{} depth {}: expected {}, counted {}",
+                    fen, depth, expected, nodes
+                ));
+            }
+        }
+    }
+    assert!(mismatches.is_empty(), "perft mismatches:\n{}", mismatches.join("\n"));
+}
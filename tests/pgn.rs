@@ -0,0 +1,206 @@
+use oracle::game::{GameResult, GameState};
+use oracle::moves::info::Move;
+use oracle::pgn::{PgnError, PgnGame, PgnReader};
+use oracle::uci::START_FEN;
+use oracle::utils::{Color, Square};
+
+const ANNOTATED_GAME: &str = r#"[Event "Casual game"]
+[Site "?"]
+[Date "2024.01.02"]
+[Round "1"]
+[White "Alice"]
+[Black "Bob \"The Rook\""]
+[Result "0-1"]
+
+{Fool's mate} 1. f3 $2 e5 2. g4?? (2. e4 {is sensible} Nf6 (2... Nc6)) 2...
+Qh4# ; checkmate
+0-1
+"#;
+
+#[test]
+pub fn test_parse_annotations() {
+    let pgn = PgnGame::parse(ANNOTATED_GAME).expect("failed to parse pgn");
+    assert_eq!(pgn.tag("White"), Some("Alice"));
+    assert_eq!(pgn.tag("Black"), Some("Bob \"The Rook\""));
+    assert_eq!(pgn.result, "0-1");
+
+    let sans = pgn.moves.iter().map(|mv| mv.san.as_str()).collect::<Vec<_>>();
+    assert_eq!(sans, vec!["f3", "e5", "g4", "Qh4#"]);
+    assert_eq!(pgn.moves[0].leading_comments, vec!["Fool's mate"]);
+    assert_eq!(pgn.moves[0].nags, vec![2]);
+    assert_eq!(pgn.moves[2].nags, vec![4]);
+    assert_eq!(pgn.moves[3].comments, vec!["checkmate"]);
+
+    let variation = &pgn.moves[2].variations[0];
+    assert_eq!(variation[0].san, "e4");
+    assert_eq!(variation[0].comments, vec!["is sensible"]);
+    assert_eq!(variation[1].san, "Nf6");
+    assert_eq!(variation[1].variations[0][0].san, "Nc6");
+
+    let game = pgn.to_game_state().expect("failed to replay pgn");
+    assert_eq!(game.outcome(), GameResult::Checkmate(Color::Black));
+}
+
+#[test]
+pub fn test_annotations_survive_round_trip() {
+    let pgn = PgnGame::parse(ANNOTATED_GAME).unwrap();
+    let exported = pgn.to_string();
+    assert!(exported.ends_with(
+        "\n\n{Fool's mate} 1. f3 $2 e5 2. g4 $4 (2. e4 {is sensible} 2... Nf6 (2... Nc6))\n2... Qh4# {checkmate} 0-1\n"
+    ));
+    assert_eq!(PgnGame::parse(&exported).unwrap(), pgn);
+}
+
+#[test]
+pub fn test_strip_annotations() {
+    let pgn = PgnReader::new(ANNOTATED_GAME.as_bytes())
+        .strip_annotations()
+        .next()
+        .unwrap()
+        .unwrap();
+    assert!(pgn
+        .moves
+        .iter()
+        .all(|mv| mv.nags.is_empty() && mv.comments.is_empty() && mv.variations.is_empty()));
+    assert!(pgn.to_string().ends_with("1. f3 e5 2. g4 Qh4# 0-1\n"));
+}
+
+#[test]
+pub fn test_export_game() {
+    let mut game = GameState::new(START_FEN);
+    let moves = [
+        (Square::E2, Square::E4),
+        (Square::E7, Square::E5),
+        (Square::G1, Square::F3),
+        (Square::B8, Square::C6),
+        (Square::F1, Square::B5),
+    ];
+    for (from, to) in moves {
+        game.play_move(Move::new(from, to)).expect("illegal move");
+    }
+
+    let exported = PgnGame::from_game(&game).to_string();
+    assert!(exported.starts_with("[Event \"?\"]\n"));
+    assert!(exported.contains("[Result \"*\"]\n"));
+    assert!(!exported.contains("[FEN"));
+    assert!(exported.ends_with("\n\n1. e4 e5 2. Nf3 Nc6 3. Bb5 *\n"));
+
+    let imported = PgnGame::parse(&exported).unwrap().to_game_state().unwrap();
+    assert_eq!(imported.position.key(), game.position.key());
+    assert_eq!(imported.move_history().len(), 5);
+}
+
+#[test]
+pub fn test_export_from_fen() {
+    let fen = "4k3/8/8/8/8/8/4P3/4K3 b - - 3 40";
+    let mut game = GameState::new(fen);
+    game.play_move(Move::new(Square::E8, Square::D7)).unwrap();
+    game.play_move(Move::new(Square::E2, Square::E4)).unwrap();
+
+    let exported = PgnGame::from_game(&game).to_string();
+    assert!(exported.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 3 40\"]\n"));
+    assert!(exported.ends_with("40... Kd7 41. e4 *\n"));
+
+    let imported = PgnGame::parse(&exported).unwrap().to_game_state().unwrap();
+    assert_eq!(imported.start_fen(), fen);
+    assert_eq!(imported.position.key(), game.position.key());
+}
+
+#[test]
+pub fn test_multiple_games() {
+    let text = format!(
+        "{}\n[Event \"Second\"]\n\n1. e4 e5 1/2-1/2\n\n[Event \"No result\"]\n1. d4\n[Event \"Last\"]\n\n1. c4 *\n",
+        ANNOTATED_GAME
+    );
+    let games = PgnReader::new(text.as_bytes())
+        .collect::<Result<Vec<_>, _>>()
+        .expect("failed to read games");
+
+    let events = games.iter().map(|game| game.tag("Event").unwrap()).collect::<Vec<_>>();
+    assert_eq!(events, vec!["Casual game", "Second", "No result", "Last"]);
+    assert_eq!(games[1].result, "1/2-1/2");
+    assert_eq!(games[2].result, "*");
+    assert_eq!(games[2].moves.len(), 1);
+    assert_eq!(games[3].moves[0].san, "c4");
+}
+
+#[test]
+pub fn test_replay_past_repetition() {
+    let pgn = PgnGame::parse(include_str!("data/repetition.pgn")).expect("failed to parse pgn");
+    let game = pgn.to_game_state().expect("failed to replay pgn");
+    assert_eq!(game.move_history().len(), 15);
+    assert_eq!(game.outcome(), GameResult::Checkmate(Color::White));
+}
+
+#[test]
+pub fn test_game_end_inside_comments() {
+    // results and tag lines inside comments don't end the game
+    let text =
+        "1. e4 {a comment\n[over] two lines 1-0} e5 ; 0-1\n2. Nf3 (2. f4 {*}) 1/2-1/2\n[Event \"Next\"]\n1. d4 *\n";
+    let games = PgnReader::new(text.as_bytes())
+        .collect::<Result<Vec<_>, _>>()
+        .expect("failed to read games");
+    assert_eq!(games.len(), 2);
+    assert_eq!(games[0].moves.len(), 3);
+    assert_eq!(games[0].moves[0].comments, vec!["a comment [over] two lines 1-0"]);
+    assert_eq!(games[0].result, "1/2-1/2");
+    assert_eq!(games[1].tag("Event"), Some("Next"));
+}
+
+#[test]
+pub fn test_castling_with_zeros() {
+    let text = "1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4.0-0 d6 5. d3 Be6 6. Nc3 Qd7 7. Be3 0-0-0 *\n";
+    let pgn = PgnReader::new(text.as_bytes())
+        .next()
+        .unwrap()
+        .expect("failed to read pgn");
+    assert_eq!(pgn.moves[6].san, "0-0");
+    assert_eq!(pgn.moves[13].san, "0-0-0");
+
+    let game = pgn.to_game_state().expect("failed to replay pgn");
+    let notation = game
+        .move_history()
+        .iter()
+        .map(|mv| mv.notation.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(notation[6], "O-O");
+    assert_eq!(notation[13], "O-O-O");
+}
+
+#[test]
+pub fn test_errors_stay_in_their_game() {
+    // a malformed game still ends at its result, the games after it are read normally
+    for broken in ["1. e4 @@ e5 1-0", "1. e4 e5) 2. Nf3 1-0", "1. e4 $x e5 1-0"] {
+        let text = format!("[Event \"Broken\"]\n{}\n\n[Event \"Next\"]\n1. d4 *\n", broken);
+        let games = PgnReader::new(text.as_bytes()).collect::<Vec<_>>();
+        assert_eq!(games.len(), 2, "{}", broken);
+        assert!(games[0].is_err(), "{}", broken);
+        assert_eq!(games[1].as_ref().unwrap().tag("Event"), Some("Next"), "{}", broken);
+    }
+}
+
+#[test]
+pub fn test_errors() {
+    let illegal = PgnGame::parse("1. e4 e5 2. Ke3 *").unwrap().to_game_state();
+    assert!(matches!(illegal, Err(PgnError::IllegalMove { ply: 2, ref san }) if san == "Ke3"));
+
+    let bad_fen = PgnGame::parse("[SetUp \"1\"]\n[FEN \"8/8/8/8/8/8/8/8 w - - 0 1\"]\n\n1. e4 e5 *").unwrap();
+    assert!(matches!(bad_fen.to_game_state(), Err(PgnError::InvalidFen(_))));
+    // exporting still works, the tag is kept and moves are numbered from the first
+    let exported = bad_fen.to_string();
+    assert!(exported.contains("[FEN \"8/8/8/8/8/8/8/8 w - - 0 1\"]"));
+    assert!(exported.ends_with("1. e4 e5 *\n"), "{}", exported);
+
+    assert!(matches!(
+        PgnGame::parse("1. e4 {never closed *"),
+        Err(PgnError::UnterminatedComment)
+    ));
+    assert!(matches!(
+        PgnGame::parse("1. e4 (1. d4 *"),
+        Err(PgnError::UnbalancedVariation)
+    ));
+    assert!(matches!(
+        PgnGame::parse("[Event unquoted]\n1. e4 *"),
+        Err(PgnError::InvalidTag(_))
+    ));
+}
@@ -0,0 +1,187 @@
+use oracle::bitboards::{self, Bitboard};
+use oracle::moves::generate_moves;
+use oracle::moves::info::{Move, MoveList};
+use oracle::position::Position;
+use oracle::utils::{Color, PieceType, Square};
+use std::collections::HashSet;
+
+mod common;
+
+const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[test]
+pub fn test_validated_moves_in_a_row() {
+    let mut pos = Position::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    assert!(pos.move_piece(Move::new(Square::E2, Square::E4), true).is_some());
+    assert!(pos.legal_moves_stale());
+
+    // white's move is no longer legal, black's reply is
+    assert!(pos.move_piece(Move::new(Square::D2, Square::D4), true).is_none());
+    assert!(pos.move_piece(Move::new(Square::E7, Square::E5), true).is_some());
+    assert!(pos.move_piece(Move::new(Square::G1, Square::F3), true).is_some());
+    assert_eq!(
+        pos.to_fen(),
+        "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+    );
+}
+
+#[test]
+pub fn test_queries_follow_the_board_without_recompute() {
+    let mut pos = Position::new("4k3/8/8/8/8/8/7R/4K3 w - - 0 1");
+    assert!(!pos.legal_moves_stale());
+    let undo = pos.move_piece(Move::new(Square::H2, Square::H8), true).unwrap();
+
+    // black is in check along the back rank and has to step up
+    assert!(pos.king_in_check(Color::Black));
+    assert!(pos.is_legal_move(Square::E8, Square::D7));
+    assert!(!pos.is_legal_move(Square::E8, Square::D8));
+    assert!(!bitboards::is_bit_set(
+        pos.legal_destinations_from(Square::E8),
+        Square::F8
+    ));
+
+    pos.undo_move(undo);
+    assert!(pos.legal_moves_stale());
+    assert!(!pos.king_in_check(Color::Black));
+    assert!(pos.is_legal_move(Square::H2, Square::H8));
+    // black's king moves are gone with the side to move
+    assert!(!pos.is_legal_move(Square::E8, Square::D7));
+
+    pos.compute_legal_moves();
+    assert!(!pos.legal_moves_stale());
+    assert!(pos.is_legal_move(Square::H2, Square::H8));
+}
+
+#[test]
+pub fn test_equality_ignores_caches_and_move_order() {
+    let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    let original = Position::new(fen);
+    let mut pos = original.clone();
+    assert_eq!(pos, original);
+
+    // make/unmake brings back an equal position, even though its legal move cache is stale now
+    let undo = pos.move_piece(Move::new(Square::E2, Square::A6), true).unwrap();
+    assert_ne!(pos, original);
+    pos.undo_move(undo);
+    assert_eq!(pos, original);
+
+    // the same position reached through different move orders
+    let mut knights_first = Position::new(START_FEN);
+    let mut knights_second = Position::new(START_FEN);
+    for (from, to) in [
+        (Square::G1, Square::F3),
+        (Square::G8, Square::F6),
+        (Square::B1, Square::C3),
+    ] {
+        knights_first.move_piece(Move::new(from, to), true).unwrap();
+    }
+    for (from, to) in [
+        (Square::B1, Square::C3),
+        (Square::G8, Square::F6),
+        (Square::G1, Square::F3),
+    ] {
+        knights_second.move_piece(Move::new(from, to), true).unwrap();
+    }
+    assert_eq!(knights_first, knights_second);
+
+    let mut seen = HashSet::new();
+    assert!(seen.insert(knights_first));
+    assert!(!seen.insert(knights_second));
+
+    // clocks take part
+    assert_ne!(
+        Position::new("4k3/8/8/8/8/8/8/4K3 w - - 0 1"),
+        Position::new("4k3/8/8/8/8/8/8/4K3 w - - 3 1")
+    );
+}
+
+fn squares(squares: &[Square]) -> Bitboard {
+    squares.iter().fold(0, |bb, &sq| bitboards::set_bit(bb, sq))
+}
+
+#[test]
+pub fn test_attack_queries_without_legal_moves() {
+    let mut pos = Position::new("r5k1/8/8/8/1b6/8/3N4/4K3 b - - 0 1");
+    pos.move_piece(Move::new(Square::A8, Square::E8), true);
+    assert!(pos.legal_moves_stale());
+
+    // the rook checks along the e-file, the bishop pins the knight
+    assert_eq!(pos.checkers(), squares(&[Square::E8]));
+    assert_eq!(pos.pinned(Color::White), squares(&[Square::D2]));
+    assert_eq!(pos.pinned(Color::Black), 0);
+    assert_eq!(pos.attackers_to(Square::E2, Color::Black), squares(&[Square::E8]));
+    assert_eq!(pos.attackers_to(Square::C3, Color::Black), squares(&[Square::B4]));
+    assert_eq!(pos.attackers_to(Square::B3, Color::White), squares(&[Square::D2]));
+    assert_eq!(pos.attackers_to(Square::F2, Color::White), squares(&[Square::E1]));
+    assert!(pos.legal_moves_stale());
+}
+
+#[test]
+pub fn test_gives_check() {
+    // castling rook, en passant uncovering the rook on the fifth rank, promotion
+    let castle = Position::new("5k2/8/8/8/8/8/8/4K2R w K - 0 1");
+    assert!(castle.gives_check(Move::new(Square::E1, Square::H1)));
+    let en_passant = Position::new("8/8/8/R2pP2k/8/8/8/4K3 w - d6 0 1");
+    assert!(en_passant.gives_check(Move::new(Square::E5, Square::D6)));
+    assert!(!en_passant.gives_check(Move::new(Square::E5, Square::E6)));
+    let promotion = Position::new("7k/P7/8/8/8/8/8/4K3 w - - 0 1");
+    assert!(promotion.gives_check(Move::with_promotion(Square::A7, Square::A8, PieceType::Queen)));
+    assert!(!promotion.gives_check(Move::with_promotion(Square::A7, Square::A8, PieceType::Bishop)));
+
+    // against making the move, in every position two plies into the perft suite
+    for fen in common::suite_fens() {
+        check_gives_check(&mut Position::new(fen), 2);
+    }
+}
+
+fn check_gives_check(pos: &mut Position, depth: u32) {
+    assert_eq!(
+        pos.checkers() != 0,
+        pos.king_in_check(pos.side_to_move()),
+        "{}",
+        pos.to_fen()
+    );
+
+    let mut moves = MoveList::default();
+    generate_moves(pos, &mut moves);
+    for mv in moves.iter() {
+        let gives_check = pos.gives_check(mv);
+        let undo = pos.move_piece(mv, false).expect("generated move is legal");
+        assert_eq!(
+            gives_check,
+            pos.king_in_check(pos.side_to_move()),
+            "{} after {}",
+            pos.to_fen(),
+            mv.to_uci()
+        );
+        if depth > 1 {
+            check_gives_check(pos, depth - 1);
+        }
+        pos.undo_move(undo);
+    }
+}
+
+#[test]
+pub fn test_has_legal_moves_with_stale_cache() {
+    // stalemate, mate and an ordinary reply, each asked once stale and once after a recompute
+    for (fen, mv, expected) in [
+        (
+            "7k/8/6Q1/8/8/8/8/K7 w - - 0 1",
+            Move::new(Square::G6, Square::F7),
+            false,
+        ),
+        (
+            "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1",
+            Move::new(Square::A1, Square::A8),
+            false,
+        ),
+        (START_FEN, Move::new(Square::E2, Square::E4), true),
+    ] {
+        let mut pos = Position::new(fen);
+        pos.move_piece(mv, true).expect("move is legal");
+        assert!(pos.legal_moves_stale());
+        assert_eq!(pos.has_legal_moves(), expected, "{}", fen);
+        pos.compute_legal_moves();
+        assert_eq!(pos.has_legal_moves(), expected, "{}", fen);
+    }
+}
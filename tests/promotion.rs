@@ -0,0 +1,73 @@
+use oracle::game::GameState;
+use oracle::moves::generate_moves;
+use oracle::moves::info::{Move, MoveList};
+use oracle::position::Position;
+use oracle::utils::{Piece, PieceType, Square};
+
+#[test]
+pub fn test_generates_all_promotion_pieces() {
+    let mut pos = Position::new("8/P6k/8/8/8/8/8/K7 w - - 0 1");
+    pos.compute_legal_moves();
+    let mut moves = MoveList::default();
+    generate_moves(&pos, &mut moves);
+
+    let promotions = moves
+        .iter()
+        .filter(|mv| mv.from == Square::A7)
+        .map(|mv| mv.promotion)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        promotions,
+        vec![PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight]
+    );
+}
+
+#[test]
+pub fn test_under_promotion_make_and_undo() {
+    let mut pos = Position::new("1r5k/P7/8/8/8/8/8/K7 w - - 0 1");
+    pos.compute_legal_moves();
+    let board_before = pos.board;
+    let masks_before = pos.piece_masks;
+
+    let undo = pos
+        .move_piece(Move::with_promotion(Square::A7, Square::B8, PieceType::Knight), true)
+        .expect("promotion should be legal");
+    assert_eq!(pos.board[Square::B8 as usize], Piece::WKnight);
+    assert_eq!(pos.board[Square::A7 as usize], Piece::Empty);
+
+    pos.undo_move(undo);
+    assert_eq!(pos.board, board_before);
+    assert_eq!(pos.piece_masks, masks_before);
+}
+
+#[test]
+pub fn test_promotion_notation() {
+    let mut game = GameState::new("1r5k/P7/8/8/8/8/8/K7 w - - 0 1");
+    game.position.compute_legal_moves();
+    game.play_move(Move::with_promotion(Square::A7, Square::B8, PieceType::Rook))
+        .expect("promotion should be legal");
+    assert_eq!(game.move_history()[0].notation, "axb8=R+");
+
+    // redo must replay the same promotion piece
+    assert!(game.undo_move());
+    assert!(game.redo_move());
+    assert_eq!(game.position.board[Square::B8 as usize], Piece::WRook);
+}
+
+#[test]
+pub fn test_promotion_needs_a_piece() {
+    let fen = "1r5k/P7/8/8/8/8/8/K7 w - - 0 1";
+    let mut pos = Position::new(fen);
+    assert!(pos.move_piece(Move::new(Square::A7, Square::A8), true).is_none());
+    assert!(pos.move_piece(Move::new(Square::A7, Square::B8), false).is_none());
+    assert!(pos
+        .move_piece(Move::with_promotion(Square::A7, Square::A8, PieceType::King), true)
+        .is_none());
+    assert_eq!(pos.to_fen(), fen);
+
+    let mut game = GameState::new(fen);
+    assert!(game.play_move(Move::new(Square::A7, Square::A8)).is_none());
+    let mv = Move::with_promotion(Square::A7, Square::A8, PieceType::Bishop);
+    let info = game.play_move(mv).expect("promotion should be legal");
+    assert_eq!(info.as_move(), mv);
+}
@@ -0,0 +1,159 @@
+use oracle::game;
+use oracle::moves::generate_moves;
+use oracle::moves::info::{Move, MoveInfo, MoveList};
+use oracle::position::{Position, SanError};
+use oracle::utils::{PieceType, Square};
+
+fn position(fen: &str) -> Position {
+    let mut pos = Position::new(fen);
+    pos.compute_legal_moves();
+    pos
+}
+
+#[test]
+pub fn test_parses_generated_notation() {
+    let fens = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+    ];
+    for fen in fens {
+        let pos = position(fen);
+        let mut moves = MoveList::default();
+        generate_moves(&pos, &mut moves);
+        for mv in moves.iter() {
+            let san = game::algebraic_notation(&MoveInfo::new(&pos, mv), &pos);
+            assert_eq!(pos.parse_san(&san), Ok(mv), "{} in {}", san, fen);
+        }
+    }
+}
+
+#[test]
+pub fn test_disambiguation() {
+    // rooks on a1 and a5 can both reach a3
+    let pos = position("4k3/8/8/R7/8/8/8/RN2K3 w - - 0 1");
+    assert_eq!(pos.parse_san("Na3"), Ok(Move::new(Square::B1, Square::A3)));
+    assert_eq!(pos.parse_san("R1a3"), Ok(Move::new(Square::A1, Square::A3)));
+    assert_eq!(pos.parse_san("R5a3"), Ok(Move::new(Square::A5, Square::A3)));
+    assert_eq!(pos.parse_san("Ra5a3"), Ok(Move::new(Square::A5, Square::A3)));
+    assert_eq!(pos.parse_san("Ra3"), Err(SanError::AmbiguousMove("Ra3".into())));
+
+    // knights on b1 and f3 can both reach d2
+    let pos = position("4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1");
+    assert_eq!(pos.parse_san("Nbd2"), Ok(Move::new(Square::B1, Square::D2)));
+    assert_eq!(pos.parse_san("Nfd2"), Ok(Move::new(Square::F3, Square::D2)));
+    assert_eq!(pos.parse_san("Nd2"), Err(SanError::AmbiguousMove("Nd2".into())));
+}
+
+fn notation(fen: &str, mv: Move) -> String {
+    let pos = position(fen);
+    game::algebraic_notation(&MoveInfo::new(&pos, mv), &pos)
+}
+
+#[test]
+pub fn test_generated_disambiguation() {
+    // only the rook on h1 can also reach d1, the one on h8 doesn't make a file necessary
+    let fen = "7R/8/2k5/8/8/8/4K3/R6R w - - 0 1";
+    assert_eq!(notation(fen, Move::new(Square::A1, Square::D1)), "Rad1");
+    assert_eq!(notation(fen, Move::new(Square::H1, Square::H4)), "R1h4");
+    assert_eq!(notation(fen, Move::new(Square::A1, Square::A5)), "Ra5");
+
+    // with three queens on d4 the a4 one shares its file with one and its rank with the other
+    let fen = "2k5/8/8/8/Q6Q/8/8/Q3K3 w - - 0 1";
+    assert_eq!(notation(fen, Move::new(Square::A4, Square::D4)), "Qa4d4");
+    assert_eq!(notation(fen, Move::new(Square::A1, Square::D4)), "Q1d4");
+    assert_eq!(notation(fen, Move::new(Square::H4, Square::D4)), "Qhd4");
+}
+
+#[test]
+pub fn test_check_and_mate_suffixes() {
+    assert_eq!(
+        notation("6k1/8/8/8/8/8/8/R5K1 w - - 0 1", Move::new(Square::A1, Square::A8)),
+        "Ra8+"
+    );
+    assert_eq!(
+        notation("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", Move::new(Square::A1, Square::A8)),
+        "Ra8#"
+    );
+    assert_eq!(
+        notation("4k3/8/8/8/8/8/8/4K2R w K - 0 1", Move::new(Square::E1, Square::H1)),
+        "O-O"
+    );
+    assert_eq!(
+        notation("5k2/8/8/8/8/8/8/4K2R w K - 0 1", Move::new(Square::E1, Square::H1)),
+        "O-O+"
+    );
+    assert_eq!(
+        notation(
+            "7k/1P6/8/8/8/8/8/K7 w - - 0 1",
+            Move::with_promotion(Square::B7, Square::B8, PieceType::Knight)
+        ),
+        "b8=N"
+    );
+    assert_eq!(
+        notation(
+            "7k/1P6/8/8/8/8/8/K7 w - - 0 1",
+            Move::with_promotion(Square::B7, Square::B8, PieceType::Rook)
+        ),
+        "b8=R+"
+    );
+    // the pawn keeps blocking the rook, the bishop uncovers it
+    assert_eq!(
+        notation("4k3/8/8/8/8/8/4P3/4R1K1 w - - 0 1", Move::new(Square::E2, Square::E4)),
+        "e4"
+    );
+    assert_eq!(
+        notation("4k3/8/8/8/8/8/4B3/4R1K1 w - - 0 1", Move::new(Square::E2, Square::D3)),
+        "Bd3+"
+    );
+}
+
+#[test]
+pub fn test_captures_castling_and_suffixes() {
+    let pos = position("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1");
+    assert_eq!(pos.parse_san("exd6"), Ok(Move::new(Square::E5, Square::D6)));
+    assert_eq!(pos.parse_san("O-O"), Ok(Move::new(Square::E1, Square::H1)));
+    assert_eq!(pos.parse_san("0-0-0"), Ok(Move::new(Square::E1, Square::A1)));
+    assert_eq!(pos.parse_san("Rxa8+"), Ok(Move::new(Square::A1, Square::A8)));
+    assert_eq!(pos.parse_san("Rxa8!?"), Ok(Move::new(Square::A1, Square::A8)));
+    assert_eq!(pos.parse_san("e6"), Ok(Move::new(Square::E5, Square::E6)));
+    assert_eq!(pos.parse_san("Rxa7"), Err(SanError::IllegalMove("Rxa7".into())));
+
+    let pos = position("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+    assert_eq!(pos.parse_san("Ra8#"), Ok(Move::new(Square::A1, Square::A8)));
+    assert_eq!(pos.parse_san("O-O"), Err(SanError::IllegalMove("O-O".into())));
+}
+
+#[test]
+pub fn test_promotions() {
+    let pos = position("1r5k/P7/8/8/8/8/8/K7 w - - 0 1");
+    assert_eq!(
+        pos.parse_san("a8=N"),
+        Ok(Move::with_promotion(Square::A7, Square::A8, PieceType::Knight))
+    );
+    assert_eq!(
+        pos.parse_san("axb8=Q+"),
+        Ok(Move::with_promotion(Square::A7, Square::B8, PieceType::Queen))
+    );
+    assert_eq!(
+        pos.parse_san("axb8R"),
+        Ok(Move::with_promotion(Square::A7, Square::B8, PieceType::Rook))
+    );
+    assert_eq!(pos.parse_san("a8"), Err(SanError::MissingPromotion("a8".into())));
+    assert_eq!(pos.parse_san("a8=K"), Err(SanError::InvalidPromotion("a8=K".into())));
+    assert_eq!(pos.parse_san("Kb1=Q"), Err(SanError::InvalidPromotion("Kb1=Q".into())));
+}
+
+#[test]
+pub fn test_malformed() {
+    let pos = position("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    assert_eq!(pos.parse_san(""), Err(SanError::Empty));
+    assert_eq!(pos.parse_san("+"), Err(SanError::Empty));
+    assert_eq!(pos.parse_san("Nz3"), Err(SanError::Malformed("Nz3".into())));
+    assert_eq!(pos.parse_san("e9"), Err(SanError::Malformed("e9".into())));
+    assert_eq!(pos.parse_san("xe4"), Err(SanError::Malformed("xe4".into())));
+    assert_eq!(pos.parse_san("Nb1b1b1"), Err(SanError::Malformed("Nb1b1b1".into())));
+    assert_eq!(pos.parse_san("e5"), Err(SanError::IllegalMove("e5".into())));
+    assert_eq!(pos.parse_san("Ke2"), Err(SanError::IllegalMove("Ke2".into())));
+}
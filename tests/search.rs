@@ -0,0 +1,113 @@
+use oracle::moves::info::Move;
+use oracle::position::Position;
+use oracle::search::{self, SearchLimits, MATE_SCORE};
+use oracle::transposition::TranspositionTable;
+use oracle::utils::Square;
+use std::sync::atomic::Ordering;
+
+fn search_depth(fen: &str, depth: u32) -> search::SearchResult {
+    let mut pos = Position::new(fen);
+    let limits = SearchLimits {
+        depth,
+        ..SearchLimits::default()
+    };
+    search::search(&mut pos, &limits)
+}
+
+#[test]
+pub fn test_finds_back_rank_mate() {
+    let result = search_depth("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3);
+    assert_eq!(result.best_move, Some(Move::new(Square::A1, Square::A8)));
+    assert_eq!(result.mate_in(), Some(1));
+    assert_eq!(result.score, MATE_SCORE - 1);
+}
+
+#[test]
+pub fn test_finds_mate_in_two() {
+    // 1. Rh8+ lets the king escape to a7, 1. Kb6 Kb8 2. Rh8# does not
+    let result = search_depth("k7/8/2K5/8/8/8/8/7R w - - 0 1", 4);
+    assert_eq!(result.mate_in(), Some(2));
+    assert_eq!(result.pv.len(), 3);
+}
+
+#[test]
+pub fn test_captures_hanging_queen() {
+    let result = search_depth("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1", 2);
+    assert_eq!(result.best_move, Some(Move::new(Square::D1, Square::D5)));
+}
+
+#[test]
+pub fn test_quiescence_sees_recapture() {
+    // at depth 1 Qxd5 wins a pawn until exd5 is looked at
+    let result = search_depth("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1", 1);
+    assert_ne!(result.best_move, Some(Move::new(Square::D1, Square::D5)));
+    assert!(result.score > 0);
+
+    // a defended capture that still wins material is played
+    let result = search_depth("4k3/8/4p3/3r4/8/4N3/8/4K3 w - - 0 1", 1);
+    assert_eq!(result.best_move, Some(Move::new(Square::E3, Square::D5)));
+}
+
+#[test]
+pub fn test_quiescence_finds_mate_in_check() {
+    // the mate is delivered on the last ply, only the evasion path notices there is no way out
+    let result = search_depth("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 1);
+    assert_eq!(result.best_move, Some(Move::new(Square::A1, Square::A8)));
+    assert_eq!(result.mate_in(), Some(1));
+}
+
+#[test]
+pub fn test_no_legal_moves() {
+    let checkmated = search_depth("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3", 3);
+    assert_eq!(checkmated.best_move, None);
+    assert_eq!(checkmated.score, -MATE_SCORE);
+
+    let stalemated = search_depth("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", 3);
+    assert_eq!(stalemated.best_move, None);
+    assert_eq!(stalemated.score, 0);
+}
+
+#[test]
+pub fn test_search_restores_position() {
+    let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    let mut pos = Position::new(fen);
+    let board_before = pos.board;
+    search::search(
+        &mut pos,
+        &SearchLimits {
+            depth: 2,
+            ..SearchLimits::default()
+        },
+    );
+    assert_eq!(pos.board, board_before);
+    assert_eq!(pos.en_passant_sq(), "-");
+}
+
+#[test]
+pub fn test_stopped_search_still_returns_move() {
+    let limits = SearchLimits::default();
+    limits.stop.store(true, Ordering::Relaxed);
+    let mut pos = Position::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    let result = search::search(&mut pos, &limits);
+    assert!(result.best_move.is_some());
+    assert_eq!(result.depth, 0);
+}
+
+#[test]
+pub fn test_table_is_reused_between_searches() {
+    let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    let limits = SearchLimits {
+        depth: 4,
+        ..SearchLimits::default()
+    };
+    let mut table = TranspositionTable::new(8);
+
+    let first = search::search_with_info(&mut Position::new(fen), &limits, &mut table, |_| {});
+    assert!(table.stats().hits > 0);
+    assert!(first.hashfull > 0);
+
+    // the second search starts from the first one's results and visits fewer nodes
+    let second = search::search_with_info(&mut Position::new(fen), &limits, &mut table, |_| {});
+    assert_eq!(second.score, first.score);
+    assert!(second.nodes < first.nodes);
+}
@@ -0,0 +1,82 @@
+use oracle::moves::info::Move;
+use oracle::position::Position;
+use oracle::utils::{PieceType, Square};
+
+fn see(fen: &str, mv: Move) -> i32 {
+    Position::new(fen).see(mv)
+}
+
+// https://www.chessprogramming.org/SEE_-_The_Swap_Algorithm
+#[test]
+pub fn test_exchanges() {
+    // undefended pawn
+    assert_eq!(
+        see(
+            "1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1",
+            Move::new(Square::E1, Square::E5)
+        ),
+        100
+    );
+    // knight for a pawn, the queen on h8 joins behind the bishop
+    assert_eq!(
+        see(
+            "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+            Move::new(Square::D3, Square::E5)
+        ),
+        100 - 320
+    );
+    // equal trade
+    assert_eq!(
+        see("4k3/8/3r4/8/8/8/3R4/4K3 w - - 0 1", Move::new(Square::D2, Square::D6)),
+        500
+    );
+    assert_eq!(
+        see("4k3/2p5/3r4/8/8/8/3R4/4K3 w - - 0 1", Move::new(Square::D2, Square::D6)),
+        0
+    );
+    // a quiet move onto an attacked square loses the piece
+    assert_eq!(
+        see("4k3/8/8/2p5/8/8/8/3QK3 w - - 0 1", Move::new(Square::D1, Square::D4)),
+        -900
+    );
+    assert_eq!(
+        see("4k3/8/8/8/8/8/8/3QK3 w - - 0 1", Move::new(Square::D1, Square::D4)),
+        0
+    );
+}
+
+#[test]
+pub fn test_king_recaptures() {
+    // the king takes the queen back
+    assert_eq!(
+        see("8/8/4k3/3p4/8/8/8/3QK3 w - - 0 1", Move::new(Square::D1, Square::D5)),
+        100 - 900
+    );
+    // unless the rook behind the queen defends d5
+    assert_eq!(
+        see("8/8/4k3/3p4/8/8/3Q4/3RK3 w - - 0 1", Move::new(Square::D2, Square::D5)),
+        100
+    );
+}
+
+#[test]
+pub fn test_special_moves() {
+    let en_passant = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1";
+    assert_eq!(see(en_passant, Move::new(Square::E5, Square::D6)), 100);
+
+    let promotion = Move::with_promotion(Square::B7, Square::B8, PieceType::Queen);
+    assert_eq!(see("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", promotion), 800);
+    // the rook takes the new queen, taking the rook instead wins it and an upgraded pawn
+    let defended = "r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1";
+    assert_eq!(see(defended, promotion), -100);
+    assert_eq!(
+        see(
+            defended,
+            Move::with_promotion(Square::B7, Square::A8, PieceType::Knight)
+        ),
+        500 + 220
+    );
+
+    let castle = Move::new(Square::E1, Square::H1);
+    assert_eq!(see("4k3/8/8/8/8/8/8/4K2R w K - 0 1", castle), 0);
+}
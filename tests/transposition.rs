@@ -0,0 +1,76 @@
+use oracle::moves::info::Move;
+use oracle::moves::{count_legal_moves, count_legal_moves_hashed, perft_divide, perft_divide_hashed};
+use oracle::position::Position;
+use oracle::transposition::{Bound, PerftTable, SearchEntry, TranspositionTable};
+use oracle::utils::Square;
+
+fn entry(score: i32) -> SearchEntry {
+    SearchEntry {
+        score,
+        bound: Bound::Exact,
+        best_move: Some(Move::new(Square::E2, Square::E4)),
+    }
+}
+
+#[test]
+pub fn test_store_and_probe() {
+    let mut table = TranspositionTable::new(1);
+    assert!(table.len().is_power_of_two());
+    assert!(table.probe(42).is_none());
+
+    table.store(42, 3, entry(17));
+    let found = table.probe(42).expect("stored entry is gone");
+    assert_eq!(found.depth, 3);
+    assert_eq!(found.data, entry(17));
+
+    // same slot, different position
+    assert!(table.probe(42 + table.len() as u64).is_none());
+
+    let stats = table.stats();
+    assert_eq!((stats.probes, stats.hits, stats.stores), (3, 1, 1));
+    assert!((stats.hit_rate() - 1.0 / 3.0).abs() < 1e-9);
+
+    table.clear();
+    assert!(table.probe(42).is_none());
+}
+
+#[test]
+pub fn test_replacement_prefers_depth_then_age() {
+    let mut table = TranspositionTable::new(1);
+    let other = 7 + table.len() as u64;
+
+    table.store(7, 5, entry(1));
+    // a shallower entry for another position doesn't evict a deeper one of the same search
+    table.store(other, 2, entry(2));
+    assert_eq!(table.probe(7).unwrap().data, entry(1));
+    assert!(table.probe(other).is_none());
+
+    // but does once the deep entry has aged
+    table.new_search();
+    table.store(other, 2, entry(2));
+    assert!(table.probe(7).is_none());
+    assert_eq!(table.probe(other).unwrap().data, entry(2));
+    assert_eq!(table.stats().overwrites, 1);
+}
+
+#[test]
+pub fn test_hashed_perft_matches() {
+    let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    let mut pos = Position::new(fen);
+    let expected = count_legal_moves(&mut pos, 4);
+
+    // a tiny table keeps overwriting entries, the counts must not change
+    let mut table = PerftTable::new(1);
+    assert_eq!(count_legal_moves_hashed(&mut pos, 4, &mut table), expected);
+    assert!(table.stats().hits > 0);
+    assert_eq!(pos.to_fen(), Position::new(fen).to_fen());
+
+    // a second run is answered from the table
+    let hits = table.stats().hits;
+    assert_eq!(count_legal_moves_hashed(&mut pos, 4, &mut table), expected);
+    assert_eq!(table.stats().hits, hits + 1);
+
+    let mut pos = Position::new("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1");
+    let mut table = PerftTable::new(1);
+    assert_eq!(perft_divide_hashed(&mut pos, 4, &mut table), perft_divide(&mut pos, 4));
+}
@@ -0,0 +1,178 @@
+use oracle::moves::info::Move;
+use oracle::position::Position;
+use oracle::uci;
+use oracle::utils::{PieceType, Square};
+use std::io::{Cursor, Write};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+fn run_transcript(transcript: &str) -> Vec<String> {
+    let mut output = Vec::new();
+    uci::run(Cursor::new(transcript), &mut output).expect("uci loop failed");
+    String::from_utf8(output)
+        .expect("uci output is not utf-8")
+        .lines()
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+pub fn test_handshake() {
+    let output = run_transcript("uci\nisready\nquit\n");
+    assert!(output[0].starts_with("id name Oracle"));
+    assert!(output[1].starts_with("id author"));
+    assert!(output[2].starts_with("option name Hash type spin"));
+    assert_eq!(output[3], "option name UCI_Chess960 type check default false");
+    assert_eq!(output[4], "uciok");
+    assert_eq!(output[5], "readyok");
+    assert_eq!(output.len(), 6);
+}
+
+#[test]
+pub fn test_go_returns_legal_move() {
+    let output = run_transcript("position startpos moves e2e4 e7e5\ngo depth 2\n");
+    let best_move = output
+        .last()
+        .expect("no bestmove")
+        .strip_prefix("bestmove ")
+        .expect("no bestmove");
+
+    // replaying the answer on top of the game must be accepted
+    let output = run_transcript(&format!(
+        "position startpos moves e2e4 e7e5 {}\ngo depth 1\n",
+        best_move
+    ));
+    assert!(output.last().unwrap().starts_with("bestmove "));
+    assert_ne!(output.last().unwrap(), "bestmove 0000");
+}
+
+#[test]
+pub fn test_position_fen_with_moves() {
+    // fool's mate, white has no legal moves left
+    let output = run_transcript(
+        "position fen rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 moves f2f3 e7e5 g2g4 d8h4\ngo\n",
+    );
+    assert_eq!(output, vec!["bestmove 0000"]);
+}
+
+#[test]
+pub fn test_position_moves_past_claimable_draw() {
+    // the draws are reached along the way but nobody claims them, both games go on to a mate in one
+    let output = run_transcript("position fen 7k/5ppp/8/8/8/8/R7/6K1 w - - 99 80 moves a2b2 h8g8\ngo depth 2\n");
+    assert_eq!(output.last().unwrap(), "bestmove b2b8");
+
+    let output = run_transcript(
+        "position startpos moves g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8 e2e4 e7e5 d1h5 b8c6 f1c4 g8f6\ngo depth 2\n",
+    );
+    assert_eq!(output.last().unwrap(), "bestmove h5f7");
+}
+
+#[test]
+pub fn test_invalid_position_is_reported() {
+    // the previous position is kept
+    let output = run_transcript("position startpos moves e2e4 e7e5\nposition startpos moves e2e4 e7e4\ngo depth 1\n");
+    assert_eq!(output[0], "info string illegal move e7e4");
+    let best_move = output.last().unwrap().strip_prefix("bestmove ").unwrap();
+    let pos = Position::new("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2");
+    assert!(pos.parse_uci_move(best_move).is_some());
+
+    let output = run_transcript("position startpos e2e4\n");
+    assert_eq!(output, vec!["info string expected moves after startpos, got 'e2e4'"]);
+}
+
+#[test]
+pub fn test_go_reports_mate() {
+    let output = run_transcript("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\ngo depth 2\n");
+    assert!(output.iter().any(|line| line.starts_with("info depth 2 score mate 1")));
+    assert_eq!(output.last().unwrap(), "bestmove a1a8");
+}
+
+#[test]
+pub fn test_stop_infinite_search() {
+    let output = run_transcript("position startpos\ngo infinite\nstop\nisready\n");
+    assert!(output.iter().any(|line| line.starts_with("bestmove ")));
+    assert_eq!(output.last().unwrap(), "readyok");
+}
+
+#[test]
+pub fn test_set_hash_size() {
+    // malformed values are ignored like any other bad argument
+    let output = run_transcript(
+        "setoption name Hash value 1\nsetoption name Hash value x\nucinewgame\nposition startpos\ngo depth 3\n",
+    );
+    assert!(output
+        .iter()
+        .any(|line| line.starts_with("info depth 3 ") && line.contains(" hashfull ")));
+    assert!(output.last().unwrap().starts_with("bestmove "));
+}
+
+#[test]
+pub fn test_quit_stops_bounded_search() {
+    // a GUI that sends quit doesn't wait for the search to run out
+    let start = Instant::now();
+    let output = run_transcript("position startpos\ngo depth 64\nquit\n");
+    assert!(
+        start.elapsed() < Duration::from_secs(5),
+        "quit took {:?}",
+        start.elapsed()
+    );
+    assert!(output.last().unwrap().starts_with("bestmove "));
+}
+
+#[test]
+pub fn test_commands_after_quit_are_ignored() {
+    let output = run_transcript("isready\nquit\nisready\n");
+    assert_eq!(output, vec!["readyok"]);
+}
+
+#[test]
+pub fn test_binary_over_stdio() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_oracle"))
+        .arg("--uci")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to start oracle");
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"uci\nucinewgame\nisready\nposition startpos\ngo depth 2\n")
+        .unwrap();
+    let output = child.wait_with_output().expect("oracle did not exit");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert!(lines.contains(&"uciok"));
+    assert!(lines.contains(&"readyok"));
+    assert!(lines.last().unwrap().starts_with("bestmove "));
+}
+
+#[test]
+pub fn test_uci_move_conversion() {
+    let mut pos = Position::new("1r5k/P7/8/8/8/8/4P3/K7 w - - 0 1");
+    pos.compute_legal_moves();
+
+    let push = pos.parse_uci_move("e2e4").expect("e2e4 is legal");
+    assert_eq!(push, Move::new(Square::E2, Square::E4));
+    assert_eq!(push.to_uci(), "e2e4");
+
+    let promotion = pos.parse_uci_move("a7b8n").expect("a7b8n is legal");
+    assert_eq!(
+        promotion,
+        Move::with_promotion(Square::A7, Square::B8, PieceType::Knight)
+    );
+    assert_eq!(promotion.to_uci(), "a7b8n");
+
+    for invalid in ["e2e5", "a7b8", "e2e3q", "a7b8k", "e2", "e2e4 ", "i2i4", "0000"] {
+        assert_eq!(pos.parse_uci_move(invalid), None, "{}", invalid);
+    }
+    assert_eq!(Move::default().to_uci(), "0000");
+
+    assert_eq!(Square::H8.to_string(), "h8");
+    assert_eq!(Square::Count.to_string(), "-");
+    assert_eq!(Square::parse("c3"), Some(Square::C3));
+    assert_eq!(Square::parse("c9"), None);
+}
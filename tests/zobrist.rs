@@ -0,0 +1,115 @@
+use oracle::moves::generate_moves;
+use oracle::moves::info::{Move, MoveList};
+use oracle::position::Position;
+use oracle::utils::Square;
+
+// Walk the whole move tree and check the incremental key against a from scratch computation after every make/unmake
+fn verify_keys(pos: &mut Position, depth: u32) {
+    if depth == 0 {
+        return;
+    }
+
+    pos.compute_legal_moves();
+    let mut moves = MoveList::default();
+    generate_moves(pos, &mut moves);
+    for mv in moves.iter() {
+        let key_before = pos.key();
+        let undo = pos.move_piece(mv, false).unwrap();
+        assert_eq!(
+            pos.key(),
+            pos.compute_key(),
+            "key mismatch after {:?} -> {:?}",
+            mv.from,
+            mv.to
+        );
+        assert_ne!(pos.key(), key_before);
+
+        verify_keys(pos, depth - 1);
+
+        pos.undo_move(undo);
+        assert_eq!(
+            pos.key(),
+            key_before,
+            "key not restored after undoing {:?} -> {:?}",
+            mv.from,
+            mv.to
+        );
+        assert_eq!(pos.key(), pos.compute_key());
+    }
+}
+
+#[test]
+pub fn test_incremental_key_matches_recomputed_key() {
+    let fens = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+    ];
+    for fen in fens {
+        let mut pos = Position::new(fen);
+        assert_eq!(pos.key(), pos.compute_key());
+        verify_keys(&mut pos, 3);
+    }
+}
+
+#[test]
+pub fn test_transpositions_share_a_key() {
+    let play = |pos: &mut Position, moves: &[(Square, Square)]| {
+        for &(from, to) in moves {
+            pos.compute_legal_moves();
+            pos.move_piece(Move::new(from, to), true).expect("illegal move");
+        }
+    };
+
+    let mut knights_first = Position::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    play(
+        &mut knights_first,
+        &[
+            (Square::G1, Square::F3),
+            (Square::G8, Square::F6),
+            (Square::E2, Square::E3),
+        ],
+    );
+    let mut pawn_first = Position::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    play(
+        &mut pawn_first,
+        &[
+            (Square::E2, Square::E3),
+            (Square::G8, Square::F6),
+            (Square::G1, Square::F3),
+        ],
+    );
+    assert_eq!(knights_first.key(), pawn_first.key());
+
+    // the knights going back and forth repeats the starting position
+    let start = Position::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    let mut shuffled = Position::new("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    play(
+        &mut shuffled,
+        &[
+            (Square::G1, Square::F3),
+            (Square::G8, Square::F6),
+            (Square::F3, Square::G1),
+            (Square::F6, Square::G8),
+        ],
+    );
+    assert_eq!(start.key(), shuffled.key());
+}
+
+#[test]
+pub fn test_state_is_part_of_the_key() {
+    let white = Position::new("4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+    let black = Position::new("4k3/8/8/8/8/8/8/4K2R b K - 0 1");
+    let no_castling = Position::new("4k3/8/8/8/8/8/8/4K2R w - - 0 1");
+    assert_ne!(white.key(), black.key());
+    assert_ne!(white.key(), no_castling.key());
+
+    // en passant only matters when it can be captured
+    let capturable = Position::new("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+    let capturable_no_ep = Position::new("4k3/8/8/3pP3/8/8/8/4K3 w - - 0 1");
+    let not_capturable = Position::new("4k3/8/8/3p4/8/8/4P3/4K3 w - d6 0 1");
+    let not_capturable_no_ep = Position::new("4k3/8/8/3p4/8/8/4P3/4K3 w - - 0 1");
+    assert_ne!(capturable.key(), capturable_no_ep.key());
+    assert_eq!(not_capturable.key(), not_capturable_no_ep.key());
+}